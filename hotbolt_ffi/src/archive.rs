@@ -0,0 +1,169 @@
+//! Archived state, read in place from the serialized state instead of being deserialized.
+//!
+//! The archive is the raw memory of a slice of plain data, prefixed with the layout fingerprint of
+//! its items. Restoring it borrows the serialized state handed to the library, so even huge
+//! states restore without copying until they are first changed.
+
+use std::{
+	borrow::Cow,
+	convert::TryInto,
+	marker::PhantomData,
+	mem,
+	ops::{Deref, DerefMut},
+	slice,
+};
+
+use crate::{
+	base::StateConverter,
+	common::{Deserializer, FfiArrayMut, Serializer},
+	layout::StateLayout,
+	prelude::UnsafeFrom,
+};
+
+/// Plain data that can be read in place from the memory written by another build of the library.
+///
+/// # Safety
+///
+/// The type must not contain padding, references, pointers or anything owning memory elsewhere,
+/// such as `String` or `Vec`. Values are checked against [`StateLayout`](StateLayout) only, so
+/// every value written by a type with the same layout must be valid. Use `#[repr(C)]` for structs.
+pub unsafe trait Relocatable: Copy + StateLayout + 'static {}
+
+macro_rules! RelocatablePrimitive {
+	($($t:ty),*) => {
+		$(
+			unsafe impl Relocatable for $t {}
+		)*
+	};
+}
+
+RelocatablePrimitive!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Relocatable, const N: usize> Relocatable for [T; N] {}
+
+/// State holding a list of [`Relocatable`](Relocatable) items.
+///
+/// Restored items are borrowed from the serialized state, and copied the first time they are
+/// changed through [`DerefMut`](DerefMut) or [`to_mut`](Archived::to_mut).
+pub struct Archived<T: Relocatable> {
+	items: Cow<'static, [T]>,
+}
+
+impl<T: Relocatable> Archived<T> {
+	// Magic, padding, layout and length. Together with the envelope, this keeps the items as
+	// aligned as the serialized state, up to 32 bytes.
+	const MAGIC: &'static [u8; 4] = b"HBAR";
+	const HEADER_LEN: usize = 24;
+
+	/// Returns whether the items are still read in place from the serialized state.
+	pub fn is_borrowed(&self) -> bool {
+		matches!(self.items, Cow::Borrowed(_))
+	}
+
+	/// Returns the items as a `Vec`, copying them first when they are borrowed.
+	pub fn to_mut(&mut self) -> &mut Vec<T> {
+		self.items.to_mut()
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let bytes = unsafe {
+			slice::from_raw_parts(
+				self.items.as_ptr() as *const u8,
+				mem::size_of_val::<[T]>(&self.items),
+			)
+		};
+		let mut archive = Vec::with_capacity(Self::HEADER_LEN + bytes.len());
+		archive.extend_from_slice(Self::MAGIC);
+		archive.extend_from_slice(&[0; 4]);
+		archive.extend_from_slice(&T::LAYOUT.to_le_bytes());
+		archive.extend_from_slice(&(self.items.len() as u64).to_le_bytes());
+		archive.extend_from_slice(bytes);
+		archive
+	}
+
+	/// Reads the items in place when they are aligned, and copies them otherwise.
+	fn decode<'a>(archive: &'a [u8]) -> Result<Cow<'a, [T]>, ()> {
+		if archive.len() < Self::HEADER_LEN || !archive.starts_with(Self::MAGIC) {
+			return Err(());
+		}
+		let layout = u64::from_le_bytes(archive[8..16].try_into().map_err(|_| ())?);
+		let len = u64::from_le_bytes(archive[16..24].try_into().map_err(|_| ())?);
+		let bytes = &archive[Self::HEADER_LEN..];
+		let len: usize = len.try_into().map_err(|_| ())?;
+		if layout != T::LAYOUT || len.checked_mul(mem::size_of::<T>()) != Some(bytes.len()) {
+			return Err(());
+		}
+
+		if (bytes.as_ptr() as *const T).is_aligned() {
+			return Ok(Cow::Borrowed(unsafe {
+				slice::from_raw_parts(bytes.as_ptr() as *const T, len)
+			}));
+		}
+		let mut items = Vec::<T>::with_capacity(len);
+		unsafe {
+			let items_ptr = items.as_mut_ptr() as *mut u8;
+			std::ptr::copy_nonoverlapping(bytes.as_ptr(), items_ptr, bytes.len());
+			items.set_len(len);
+		}
+		Ok(Cow::Owned(items))
+	}
+}
+
+impl<T: Relocatable> Default for Archived<T> {
+	fn default() -> Self {
+		Self {
+			items: Cow::Owned(Vec::new()),
+		}
+	}
+}
+
+impl<T: Relocatable> From<Vec<T>> for Archived<T> {
+	fn from(items: Vec<T>) -> Self {
+		Self {
+			items: Cow::Owned(items),
+		}
+	}
+}
+
+impl<T: Relocatable> Deref for Archived<T> {
+	type Target = [T];
+
+	fn deref(&self) -> &[T] {
+		&self.items
+	}
+}
+
+impl<T: Relocatable> DerefMut for Archived<T> {
+	fn deref_mut(&mut self) -> &mut [T] {
+		self.items.to_mut()
+	}
+}
+
+/// Converts [`Archived`](Archived) state to and from its archive.
+pub struct ArchiveConverter<T> {
+	phantom: PhantomData<T>,
+}
+
+impl<T: Relocatable> Serializer<Archived<T>> for ArchiveConverter<T> {
+	fn serialize(value: &Archived<T>) -> Result<FfiArrayMut<'static, u8>, ()> {
+		Ok(unsafe { FfiArrayMut::unsafe_from(value.encode()) })
+	}
+}
+
+impl<T: Relocatable> Deserializer<Archived<T>> for ArchiveConverter<T> {
+	fn deserialize(bytes: &[u8]) -> Result<Archived<T>, ()> {
+		// The bytes may not outlive the state, so the items are copied.
+		Ok(Archived::from(Archived::decode(bytes)?.into_owned()))
+	}
+
+	fn deserialize_static(bytes: &'static [u8]) -> Result<(Archived<T>, Vec<String>), ()> {
+		let items = Archived::decode(bytes)?;
+		Ok((Archived { items }, Vec::new()))
+	}
+}
+
+impl<T: Relocatable> StateConverter for Archived<T> {
+	type State = Self;
+	type Serializer = ArchiveConverter<T>;
+	type Deserializer = ArchiveConverter<T>;
+}
@@ -1,4 +1,5 @@
-use crate::common::{Deserializer, Serializer};
+pub use crate::common::{Deserializer, Serializer};
+use crate::{common::FfiArrayMut, convert::UnsafeFrom};
 
 /// Whether the functionality is enabled.
 ///
@@ -10,6 +11,32 @@ pub trait ServerEnabled {
 	fn is_server_enabled() -> bool;
 }
 
+/// Status of the build when the runner builds the project itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildStatus {
+	/// The last build succeeded or the runner is not building the project.
+	Ok,
+	/// The project is being built.
+	Building,
+	/// The last build failed, with a short summary of the first error.
+	Failed(String),
+}
+
+/// Why the library is loaded or unloaded, given to the `#[hotbolt::on_load]` and
+/// `#[hotbolt::on_unload]` hooks.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LifecycleReason {
+	/// The runner started the application.
+	Startup,
+	/// The library is replaced by a new build in the same process.
+	SoftReload,
+	/// The process is restarted, such as to load a new build or to reset the state.
+	HardRestart,
+	/// The runner is shutting down.
+	Shutdown,
+}
+
 /// Base hot reload server functionality. All servers implement this.
 ///
 /// See [`Server`](Server) for the full server implementation.
@@ -19,6 +46,28 @@ pub trait ServerBase<T: ?Sized>: ServerEnabled {
 
 	/// Restarts the client and application with the given state.
 	fn restart_hard_with<U: AsRef<T>>(&self, state: U);
+
+	/// Watches the given file or directory for changes without restarting the application.
+	///
+	/// Changed files are retrieved using [`asset_changed`](ServerBase::asset_changed). Does nothing
+	/// by default.
+	fn watch(&self, _path: &str) {}
+
+	/// Returns the next changed file under one of the paths given to [`watch`](ServerBase::watch).
+	fn asset_changed(&self) -> Option<String> {
+		None
+	}
+
+	/// Returns the status of the latest build. The current code keeps running when a build fails.
+	fn build_status(&self) -> BuildStatus {
+		BuildStatus::Ok
+	}
+
+	/// Returns true if the runner waits for the current run to return, such as to soft reload the
+	/// library. Runs that do not return on their own should check this regularly.
+	fn should_return(&self) -> bool {
+		false
+	}
 }
 
 /// Full hot reload server functionality.
@@ -32,16 +81,60 @@ pub trait Server<T: ?Sized>: ServerBase<T> {
 
 /// The serializable, volatile state of the application.
 ///
-/// See [`App`](App).
+/// Implemented for all types implementing [`Default`](Default). See [`App`](App).
 pub trait State {
 	fn new() -> Self;
 }
 
+impl<T: Default> State for T {
+	fn new() -> Self {
+		T::default()
+	}
+}
+
+// Runs without state serialize it as nothing.
+impl Serializer<()> for () {
+	fn serialize(_value: &()) -> Result<FfiArrayMut<'static, u8>, ()> {
+		Ok(unsafe { FfiArrayMut::unsafe_from(Vec::new()) })
+	}
+}
+
+impl Deserializer<()> for () {
+	fn deserialize(_bytes: &[u8]) -> Result<(), ()> {
+		Ok(())
+	}
+}
+
+impl StateConverter for () {
+	type State = ();
+	type Serializer = ();
+	type Deserializer = ();
+}
+
+/// Upgrades serialized state from one version to the next.
+pub type Migration = fn(&[u8]) -> Result<Vec<u8>, ()>;
+
 /// Helper trait pairing a state type with serialization/deserialization facilities.
 pub trait StateConverter {
 	type State: State;
 	type Serializer: Serializer<Self::State>;
 	type Deserializer: Deserializer<Self::State>;
+
+	/// Migrations upgrading serialized state, where the migration at index `N` converts state of
+	/// version `N` to version `N + 1`.
+	const MIGRATIONS: &'static [Migration] = &[];
+
+	/// The version of the serialized state. Defaults to the number of migrations.
+	///
+	/// State serialized with an older version is migrated before being deserialized. State that
+	/// cannot be migrated is reset.
+	const VERSION: u32 = Self::MIGRATIONS.len() as u32;
+
+	/// Checks the invariants of deserialized state before it is used, such as indices staying in
+	/// bounds. Rejected state is never run, and the runner restarts with other state instead.
+	fn validate(_state: &Self::State) -> Result<(), String> {
+		Ok(())
+	}
 }
 
 /// Handles application versioning.
@@ -71,13 +164,44 @@ pub trait App {
 macro_rules! MaybeUnit {
 	(#[doc = $doc:expr], $i:ident, $t:ident) => {
 		#[doc = $doc]
-		pub trait $i {}
-		impl<T: $t> $i for T {}
-		impl $i for () {}
+		pub trait $i {
+			/// Whether this is not the unit type.
+			const PRESENT: bool;
+		}
+
+		impl<T: $t> $i for T {
+			const PRESENT: bool = true;
+		}
+
+		impl $i for () {
+			const PRESENT: bool = false;
+		}
 	};
 }
 
-MaybeUnit!(#[doc = "Type that is either [`App`](App) or a unit type indicating absence."], MaybeApp, App);
+/// Type that is either [`App`](App) or a unit type indicating absence.
+pub trait MaybeApp {
+	/// Whether this is not the unit type.
+	const PRESENT: bool;
+
+	/// Creates the app, see [`App::new`](App::new).
+	fn maybe_new() -> Self;
+}
+
+impl<T: App> MaybeApp for T {
+	const PRESENT: bool = true;
+
+	fn maybe_new() -> Self {
+		T::new()
+	}
+}
+
+impl MaybeApp for () {
+	const PRESENT: bool = false;
+
+	fn maybe_new() -> Self {}
+}
+
 MaybeUnit!(#[doc = "Type that is either [`AppVersion`](AppVersion) or a unit type indicating absence."], MaybeAppVersion, AppVersion);
 
 /// The main entry point for your application.
@@ -78,6 +78,10 @@ impl<'a, T, U: Pointer<T>> RawFfiArray<'a, T, U> {
 
 	/// Casts the array into a native Rust [slice](std::slice).
 	pub unsafe fn as_slice(&self) -> &[T] {
+		// Empty arrays are not necessarily backed by an allocation.
+		if self.is_empty() {
+			return &[];
+		}
 		std::slice::from_raw_parts(self.data.as_const_ptr(), self.len)
 	}
 }
@@ -138,6 +142,16 @@ impl<'a, T> From<&'a T> for FfiArray<'a, T> {
 	}
 }
 
+impl<'a, T> UnsafeFrom<FfiArray<'a, T>> for &'a [T] {
+	unsafe fn unsafe_from(array: FfiArray<'a, T>) -> Self {
+		// Empty arrays are not necessarily backed by an allocation.
+		if array.is_empty() {
+			return &[];
+		}
+		std::slice::from_raw_parts(array.data, array.len)
+	}
+}
+
 impl<T> UnsafeFrom<Vec<T>> for FfiArrayMut<'static, T> {
 	unsafe fn unsafe_from(vec: Vec<T>) -> Self {
 		// TODO: https://github.com/rust-lang/rust/issues/65816
@@ -165,6 +179,10 @@ impl<T> UnsafeFrom<Box<T>> for FfiArrayMut<'static, T> {
 
 impl<T> UnsafeFrom<FfiArrayMut<'static, T>> for Vec<T> {
 	unsafe fn unsafe_from(array: FfiArrayMut<'static, T>) -> Self {
+		// Arrays from `empty` have no allocation to take ownership of.
+		if array.data.is_null() {
+			return Vec::new();
+		}
 		Vec::from_raw_parts(array.data, array.len, array.capacity)
 	}
 }
@@ -185,4 +203,16 @@ pub trait Serializer<T> {
 pub trait Deserializer<T> {
 	/// Perform the conversion.
 	fn deserialize(bytes: &[u8]) -> Result<T, ()>;
+
+	/// Perform the conversion, also returning notes on parts of the value that could not be
+	/// restored as-is, such as fields that were defaulted or dropped.
+	fn deserialize_noted(bytes: &[u8]) -> Result<(T, Vec<String>), ()> {
+		Self::deserialize(bytes).map(|value| (value, Vec::new()))
+	}
+
+	/// Perform the conversion from bytes that stay valid and unchanged while the value lives,
+	/// which lets the value borrow them instead of copying them.
+	fn deserialize_static(bytes: &'static [u8]) -> Result<(T, Vec<String>), ()> {
+		Self::deserialize_noted(bytes)
+	}
 }
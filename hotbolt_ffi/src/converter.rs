@@ -0,0 +1,97 @@
+use std::{ffi::c_void, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+	base::{State, StateConverter},
+	common::{Deserializer, FfiArray, FfiArrayMut, Serializer},
+	convert::UnsafeFrom,
+	ffi::{self, FfiStringVisitor},
+};
+
+/// Creates a new type `$i` converting any serde state using the given functions.
+macro_rules! SerdeConverter {
+	(#[doc = $doc:expr], $i:ident, $serialize:path, $deserialize:path) => {
+		#[doc = $doc]
+		pub struct $i<T>(PhantomData<T>);
+
+		impl<T: Serialize> Serializer<T> for $i<T> {
+			fn serialize(value: &T) -> Result<FfiArrayMut<'static, u8>, ()> {
+				let bytes = $serialize(value).map_err(|_| ())?;
+				Ok(unsafe { FfiArrayMut::unsafe_from(bytes) })
+			}
+		}
+
+		impl<T: DeserializeOwned> Deserializer<T> for $i<T> {
+			fn deserialize(bytes: &[u8]) -> Result<T, ()> {
+				$deserialize(bytes).map_err(|_| ())
+			}
+		}
+
+		impl<T: State + Serialize + DeserializeOwned> StateConverter for $i<T> {
+			type State = T;
+			type Serializer = Self;
+			type Deserializer = Self;
+		}
+	};
+}
+
+SerdeConverter!(#[doc = "[`StateConverter`](StateConverter) using the compact [`bincode`] format."], SerdeBincode, bincode::serialize, bincode::deserialize);
+SerdeConverter!(#[doc = "[`StateConverter`](StateConverter) using the human-readable [`serde_json`] format."], SerdeJson, serde_json::to_vec, serde_json::from_slice);
+SerdeConverter!(#[doc = "[`StateConverter`](StateConverter) using the [`rmp_serde`] MessagePack format with field names."], SerdeMessagePack, rmp_serde::to_vec_named, rmp_serde::from_slice);
+
+/// Low level JSON access to serde-backed states, used by the runner to inspect and edit state.
+///
+/// See [`ENTRY_STATE_JSON_NEW`](ffi::ENTRY_STATE_JSON_NEW).
+pub trait FfiStateJson {
+	/// Renders the state as a JSON document.
+	///
+	/// # Safety
+	///
+	/// `state_ptr` must point to a state allocated by this converter.
+	unsafe fn state_json_new(state_ptr: *const c_void) -> FfiArrayMut<'static, u8>;
+
+	/// Serializes the state described by the JSON document, passing errors to the visitor.
+	///
+	/// # Safety
+	///
+	/// `json` must be valid to read and `visit` must accept `out`.
+	unsafe fn state_json_parse(
+		json: FfiArray<'static, u8>,
+		out: *mut c_void,
+		visit: FfiStringVisitor,
+	) -> FfiArrayMut<'static, u8>;
+}
+
+impl<T: StateConverter> FfiStateJson for T
+where
+	T::State: Serialize + DeserializeOwned,
+{
+	unsafe fn state_json_new(state_ptr: *const c_void) -> FfiArrayMut<'static, u8> {
+		let state: &T::State = unsafe { &*state_ptr.cast() };
+		serde_json::to_vec_pretty(state)
+			.map(|json| unsafe { FfiArrayMut::unsafe_from(json) })
+			.unwrap_or(FfiArrayMut::<u8>::empty())
+	}
+
+	unsafe fn state_json_parse(
+		json: FfiArray<u8>,
+		out: *mut c_void,
+		visit: FfiStringVisitor,
+	) -> FfiArrayMut<'static, u8> {
+		let serialized = serde_json::from_slice::<T::State>(unsafe { json.as_slice() })
+			.map_err(|e| format!("Invalid state: {}", e))
+			.and_then(|state| {
+				T::validate(&state).map_err(|e| format!("Rejected state: {}", e))?;
+				ffi::serialize_state::<T>(&state)
+					.map_err(|()| "Unable to serialize state".to_owned())
+			});
+		match serialized {
+			Ok(bytes) => unsafe { FfiArrayMut::unsafe_from(bytes) },
+			Err(e) => {
+				unsafe { visit(out, e.as_str().into()) };
+				FfiArrayMut::<u8>::empty()
+			}
+		}
+	}
+}
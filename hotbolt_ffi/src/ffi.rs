@@ -1,9 +1,32 @@
-use std::{ffi::c_void, marker::PhantomData};
+use std::{
+	any,
+	ffi::c_void,
+	fmt,
+	marker::PhantomData,
+	mem,
+	ptr::{self, NonNull},
+	slice,
+	sync::atomic::AtomicPtr,
+};
 
 use crate::{
-	base::{App, AppVersion, Run, Server, ServerBase, ServerEnabled, State, StateConverter},
+	base::{
+		App,
+		AppVersion,
+		BuildStatus,
+		MaybeApp,
+		MaybeAppVersion,
+		Migration,
+		Run,
+		Server,
+		ServerBase,
+		ServerEnabled,
+		State,
+		StateConverter,
+	},
 	common::{Deserializer, FfiArray, FfiArrayMut, Serializer},
-	convert::UnsafeInto,
+	convert::{UnsafeFrom, UnsafeInto},
+	layout::StateLayout,
 };
 
 /// Server object sent over FFI. See [`Server`](Server).
@@ -13,29 +36,64 @@ pub struct FfiServer {
 	pub server: *const c_void,
 	pub restart_hard: unsafe extern "C" fn(server_ptr: *const c_void),
 	pub restart_hard_with:
-		unsafe extern "C" fn(server_ptr: *const c_void, state: FfiArrayMut<'static, u8>),
+		unsafe extern "C" fn(server_ptr: *const c_void, state: FfiArray<'_, u8>),
 	pub restart_soft: unsafe extern "C" fn(server_ptr: *const c_void),
 	pub restart_soft_with:
-		unsafe extern "C" fn(server_ptr: *const c_void, state: FfiArrayMut<'static, u8>),
+		unsafe extern "C" fn(server_ptr: *const c_void, state: FfiArray<'_, u8>),
+	pub watch: unsafe extern "C" fn(server_ptr: *const c_void, path: FfiArray<'static, u8>),
+	pub asset_changed:
+		unsafe extern "C" fn(server_ptr: *const c_void, out: *mut c_void, visit: FfiStringVisitor),
+	pub build_status: unsafe extern "C" fn(
+		server_ptr: *const c_void,
+		out: *mut c_void,
+		visit: FfiStringVisitor,
+	) -> FfiBuildStatus,
+	pub should_return: unsafe extern "C" fn(server_ptr: *const c_void) -> bool,
+}
+
+/// Low level version of [`BuildStatus`](BuildStatus). The failure summary is passed to the visitor.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FfiBuildStatus {
+	Ok,
+	Building,
+	Failed,
+}
+
+impl FfiBuildStatus {
+	/// Converts the status, using the given summary for failures.
+	pub fn with_summary(self, summary: Option<String>) -> BuildStatus {
+		match self {
+			FfiBuildStatus::Ok => BuildStatus::Ok,
+			FfiBuildStatus::Building => BuildStatus::Building,
+			FfiBuildStatus::Failed => BuildStatus::Failed(summary.unwrap_or_default()),
+		}
+	}
 }
 
-struct TypedFfiServer<T, U: Serializer<T>> {
-	phantom_t: PhantomData<T>,
-	phantom_u: PhantomData<U>,
+/// Callback used to copy a string out of the server without transferring ownership.
+pub type FfiStringVisitor = unsafe extern "C" fn(out: *mut c_void, value: FfiArray<'static, u8>);
+
+unsafe extern "C" fn visit_string(out: *mut c_void, value: FfiArray<'static, u8>) {
+	let out = &mut *(out as *mut Option<String>);
+	*out = Some(String::from_utf8_lossy(value.as_slice()).into_owned());
+}
+
+struct TypedFfiServer<T: StateConverter> {
+	phantom: PhantomData<T>,
 	ffi_server: FfiServer,
 }
 
-impl<T, U: Serializer<T>> TypedFfiServer<T, U> {
+impl<T: StateConverter> TypedFfiServer<T> {
 	fn from(ffi_server: FfiServer) -> Self {
 		TypedFfiServer {
-			phantom_t: PhantomData,
-			phantom_u: PhantomData,
+			phantom: PhantomData,
 			ffi_server,
 		}
 	}
 }
 
-impl<T, U: Serializer<T>> ServerEnabled for TypedFfiServer<T, U> {
+impl<T: StateConverter> ServerEnabled for TypedFfiServer<T> {
 	#[inline(always)]
 	fn is_server_enabled() -> bool {
 		true
@@ -43,30 +101,68 @@ impl<T, U: Serializer<T>> ServerEnabled for TypedFfiServer<T, U> {
 }
 
 // TODO: Maybe expect/serialization errors can be more explicit
-impl<T, S: Serializer<T>> ServerBase<T> for TypedFfiServer<T, S> {
+impl<T: StateConverter> ServerBase<T::State> for TypedFfiServer<T> {
 	fn restart_hard(&self) {
 		unsafe { (self.ffi_server.restart_hard)(self.ffi_server.server) }
 	}
 
-	fn restart_hard_with<U: AsRef<T>>(&self, state: U) {
-		let serialized = S::serialize(state.as_ref()).expect("Serialized failed");
-		unsafe { (self.ffi_server.restart_hard_with)(self.ffi_server.server, serialized) }
+	fn restart_hard_with<U: AsRef<T::State>>(&self, state: U) {
+		let serialized = serialize_state::<T>(state.as_ref()).expect("Serialized failed");
+		unsafe { (self.ffi_server.restart_hard_with)(self.ffi_server.server, (&serialized).into()) }
+	}
+
+	fn watch(&self, path: &str) {
+		unsafe { (self.ffi_server.watch)(self.ffi_server.server, path.into()) }
+	}
+
+	fn asset_changed(&self) -> Option<String> {
+		let mut path: Option<String> = None;
+		let out = &mut path as *mut Option<String> as *mut c_void;
+		unsafe { (self.ffi_server.asset_changed)(self.ffi_server.server, out, visit_string) };
+		path
+	}
+
+	fn build_status(&self) -> BuildStatus {
+		let mut summary: Option<String> = None;
+		let out = &mut summary as *mut Option<String> as *mut c_void;
+		unsafe { (self.ffi_server.build_status)(self.ffi_server.server, out, visit_string) }
+			.with_summary(summary)
+	}
+
+	fn should_return(&self) -> bool {
+		unsafe { (self.ffi_server.should_return)(self.ffi_server.server) }
 	}
 }
 
-impl<T, S: Serializer<T>> Server<T> for TypedFfiServer<T, S> {
+impl<T: StateConverter> Server<T::State> for TypedFfiServer<T> {
 	fn restart_soft(&self) {
 		unsafe { (self.ffi_server.restart_soft)(self.ffi_server.server) }
 	}
 
-	fn restart_soft_with<U: AsRef<T>>(&self, state: U) {
-		let serialized = S::serialize(state.as_ref()).expect("Serialized failed");
-		unsafe { (self.ffi_server.restart_soft_with)(self.ffi_server.server, serialized) }
+	fn restart_soft_with<U: AsRef<T::State>>(&self, state: U) {
+		let serialized = serialize_state::<T>(state.as_ref()).expect("Serialized failed");
+		unsafe { (self.ffi_server.restart_soft_with)(self.ffi_server.server, (&serialized).into()) }
 	}
 }
 
 /// The version of the hotbolt server that this library supports.
-pub const SERVER_VERSION: u8 = 0;
+pub const SERVER_VERSION: u8 = 6;
+
+/// Separates the symbol of an entry point from the name of its entry, see
+/// [`entry_symbol`](entry_symbol).
+pub const ENTRY_NAME_SEPARATOR: &str = "__";
+
+/// Returns the symbol of an entry point exported by the given entry, or by the unnamed entry.
+///
+/// Named entries let one library export several main entry points, such as a server and an editor.
+/// Every `ENTRY_*` symbol of an entry is namespaced, except those shared by the whole library like
+/// [`ENTRY_ON_LOAD`](ENTRY_ON_LOAD).
+pub fn entry_symbol(symbol: &str, entry: Option<&str>) -> String {
+	match entry {
+		Some(entry) => format!("{}{}{}", symbol, ENTRY_NAME_SEPARATOR, entry),
+		None => symbol.to_owned(),
+	}
+}
 
 /// The internal hotbolt server version this was written to support.
 ///
@@ -87,10 +183,7 @@ impl<T: Run> FfiRun for T {
 		let app_typed_ptr = app_ptr as *mut T::App;
 		let app: &mut T::App = unsafe { &mut *app_typed_ptr };
 
-		let server_typed = TypedFfiServer::<
-			<T::StateConverter as StateConverter>::State,
-			<T::StateConverter as StateConverter>::Serializer,
-		>::from(server);
+		let server_typed = TypedFfiServer::<T::StateConverter>::from(server);
 		T::run(app, server_typed, state);
 	}
 }
@@ -98,10 +191,224 @@ impl<T: Run> FfiRun for T {
 /// See [`FfiRun::run`](FfiRun::run).
 pub const ENTRY_APP_RUN: &str = "hotbolt_entry_run";
 
+/// Describes what a library exports, so that the runner does not need to assume it.
+#[repr(C)]
+pub struct FfiManifest {
+	/// The server version the library was built for, see [`SERVER_VERSION`](SERVER_VERSION).
+	/// Unused by legacy main entry points.
+	pub server_version: u8,
+	/// Whether the library implements [`Run`](Run), instead of a legacy main entry point.
+	pub run: bool,
+	/// Whether the library has state to save. Zero-sized state has nothing to save.
+	pub state: bool,
+	/// Whether the library has an app, which is kept when soft reloading.
+	pub app: bool,
+	/// Whether the library checks the compatibility of the app, see [`AppVersion`](AppVersion).
+	pub app_version: bool,
+	/// Whether the library exports [`ENTRY_STATE_LAYOUT`](ENTRY_STATE_LAYOUT).
+	pub state_layout: bool,
+	/// Whether the library exports [`ENTRY_STATE_JSON_NEW`](ENTRY_STATE_JSON_NEW) and
+	/// [`ENTRY_STATE_JSON_PARSE`](ENTRY_STATE_JSON_PARSE).
+	pub state_json: bool,
+	/// The type name of the state converter, or empty.
+	pub state_converter: FfiArray<'static, u8>,
+	/// The version of the app, or empty.
+	pub version: FfiArray<'static, u8>,
+}
+
+impl FfiManifest {
+	/// Describes a library exporting `T` with its optional entry points.
+	pub fn of<T: Run>(state_layout: bool, state_json: bool) -> Self
+	where
+		T::AppVersion: FfiAppVersion,
+	{
+		let state = mem::size_of::<<T::StateConverter as StateConverter>::State>() != 0;
+		Self {
+			server_version: SERVER_VERSION,
+			run: true,
+			state,
+			app: T::App::PRESENT,
+			app_version: T::AppVersion::PRESENT,
+			state_layout,
+			state_json,
+			state_converter: any::type_name::<T::StateConverter>().into(),
+			version: T::AppVersion::app_version(),
+		}
+	}
+
+	/// Describes a library exporting a legacy main entry point.
+	pub fn legacy(state: bool) -> Self {
+		Self {
+			server_version: 0,
+			run: false,
+			state,
+			app: false,
+			app_version: false,
+			state_layout: false,
+			state_json: false,
+			state_converter: "".into(),
+			version: "".into(),
+		}
+	}
+}
+
+/// See [`FfiManifest`](FfiManifest). Optional.
+///
+/// Signature: `() -> FfiManifest`
+pub const ENTRY_MANIFEST: &str = "hotbolt_entry_manifest";
+
+/// Serialized state prefixed with the version it was serialized with.
+///
+/// See [`StateConverter::VERSION`](StateConverter::VERSION).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StateEnvelope<'a> {
+	pub version: u32,
+	pub payload: &'a [u8],
+}
+
+impl<'a> StateEnvelope<'a> {
+	const MAGIC: &'static [u8; 4] = b"HBST";
+	const HEADER_LEN: usize = 8;
+
+	/// Returns the payload prefixed with the envelope header.
+	pub fn encode(version: u32, payload: &[u8]) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(Self::HEADER_LEN + payload.len());
+		bytes.extend_from_slice(Self::MAGIC);
+		bytes.extend_from_slice(&version.to_le_bytes());
+		bytes.extend_from_slice(payload);
+		bytes
+	}
+
+	/// Reads the envelope header. Returns `None` for state serialized without an envelope.
+	pub fn decode(bytes: &'a [u8]) -> Option<Self> {
+		if bytes.len() < Self::HEADER_LEN || !bytes.starts_with(Self::MAGIC) {
+			return None;
+		}
+		let mut version = [0; 4];
+		version.copy_from_slice(&bytes[Self::MAGIC.len()..Self::HEADER_LEN]);
+		Some(Self {
+			version: u32::from_le_bytes(version),
+			payload: &bytes[Self::HEADER_LEN..],
+		})
+	}
+}
+
+pub(crate) fn serialize_state<T: StateConverter>(state: &T::State) -> Result<Vec<u8>, ()> {
+	let payload: Vec<u8> = unsafe { T::Serializer::serialize(state)?.unsafe_into() };
+	Ok(StateEnvelope::encode(T::VERSION, &payload))
+}
+
+/// Restores the state of a legacy main entry point taking `&mut State`, using the
+/// [`StateConverter`](StateConverter) of the state. State that cannot be restored is reset.
+///
+/// # Safety
+///
+/// The serialized state must outlive the returned state, which may borrow it.
+pub unsafe fn legacy_state<T: StateConverter<State = T>>(serialized: &[u8]) -> Box<T> {
+	unsafe extern "C" fn skip_note(_out: *mut c_void, _note: FfiArray<'static, u8>) {}
+
+	let serialized = slice::from_raw_parts(serialized.as_ptr(), serialized.len());
+	let state = T::state_new(serialized.into(), ptr::null_mut(), ptr::null_mut(), skip_note);
+	Box::from_raw(state.cast())
+}
+
+/// Runs the migrations needed to upgrade the payload from the given version to the last one.
+fn migrate(migrations: &[Migration], version: u32, payload: &[u8]) -> Result<Vec<u8>, ()> {
+	let mut payload = payload.to_vec();
+	for migration in &migrations[version as usize..] {
+		payload = migration(&payload)?;
+	}
+	Ok(payload)
+}
+
+/// How [`FfiState::state_new`](FfiState::state_new) obtained the state.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateRestoreKind {
+	/// No state was given, so a new state was created.
+	New,
+	/// The state was deserialized as-is.
+	Restored,
+	/// The state was migrated to the current version and deserialized.
+	Migrated,
+	/// The state was newer than the current version, so a new state was created.
+	ResetNewer,
+	/// A migration failed, so a new state was created.
+	ResetMigration,
+	/// The state could not be deserialized, so a new state was created.
+	ResetInvalid,
+	/// The state was rejected by [`StateConverter::validate`](StateConverter::validate), so a new
+	/// state was created. The reason is the only note.
+	Rejected,
+}
+
+/// Reports how state was restored, so that the runner can log it.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StateRestore {
+	pub kind: StateRestoreKind,
+	/// The version of the given serialized state.
+	pub from: u32,
+	/// The current version of the state.
+	pub to: u32,
+}
+
+impl StateRestore {
+	/// Returns true if the given state was discarded.
+	pub fn is_reset(&self) -> bool {
+		matches!(
+			self.kind,
+			StateRestoreKind::ResetNewer
+				| StateRestoreKind::ResetMigration
+				| StateRestoreKind::ResetInvalid
+				| StateRestoreKind::Rejected
+		)
+	}
+}
+
+impl fmt::Display for StateRestore {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.kind {
+			StateRestoreKind::New => write!(f, "Created new state (version {})", self.to),
+			StateRestoreKind::Restored => write!(f, "Restored state (version {})", self.to),
+			StateRestoreKind::Migrated => {
+				write!(f, "Migrated state from version {} to {}", self.from, self.to)
+			}
+			StateRestoreKind::ResetNewer => write!(
+				f,
+				"Reset state: version {} is newer than version {}",
+				self.from, self.to
+			),
+			StateRestoreKind::ResetMigration => write!(
+				f,
+				"Reset state: unable to migrate from version {} to {}",
+				self.from, self.to
+			),
+			StateRestoreKind::ResetInvalid => write!(
+				f,
+				"Reset state: unable to deserialize state (version {})",
+				self.to
+			),
+			StateRestoreKind::Rejected => write!(f, "Rejected state (version {})", self.to),
+		}
+	}
+}
+
 /// Low level version of [`State`](State).
 pub trait FfiState {
 	/// Allocates and returns a new state from the potentially given serialized state.
-	fn state_new(serialized: FfiArray<'static, u8>) -> *mut c_void;
+	///
+	/// State serialized with an older version is migrated first. How the state was obtained is
+	/// written to `restore` and notes from the deserializer are passed to the visitor.
+	///
+	/// The serialized state must stay valid and unchanged until the state is dropped, so that it
+	/// can be borrowed instead of copied, see [`Archived`](crate::archive::Archived).
+	fn state_new(
+		serialized: FfiArray<'static, u8>,
+		restore: *mut StateRestore,
+		out: *mut c_void,
+		visit: FfiStringVisitor,
+	) -> *mut c_void;
 
 	/// Drops the state. Skipped when possible. Do not use this for side-effects.
 	fn state_drop(state_ptr: *mut c_void);
@@ -114,33 +421,94 @@ pub trait FfiState {
 }
 
 impl<T: StateConverter> FfiState for T {
-	fn state_new(state_serialized: FfiArray<u8>) -> *mut c_void {
-		if !state_serialized.is_empty() {
-			let slice = unsafe { state_serialized.as_slice() };
-
-			if let Some(state) = T::Deserializer::deserialize(slice).ok() {
-				let state_ptr = Box::new(state);
-				Box::into_raw(state_ptr).cast()
-			} else {
-				Box::into_raw(Box::new(T::State::new())).cast()
+	#[allow(clippy::not_unsafe_ptr_arg_deref)]
+	fn state_new(
+		state_serialized: FfiArray<'static, u8>,
+		restore: *mut StateRestore,
+		out: *mut c_void,
+		visit: FfiStringVisitor,
+	) -> *mut c_void {
+		let slice: &'static [u8] = unsafe { state_serialized.unsafe_into() };
+		// State serialized without an envelope predates versioning.
+		let envelope = StateEnvelope::decode(slice).unwrap_or(StateEnvelope {
+			version: 0,
+			payload: slice,
+		});
+
+		let kind = |kind| StateRestore {
+			kind,
+			from: envelope.version,
+			to: T::VERSION,
+		};
+		let mut notes = Vec::new();
+		let (state, report) = if slice.is_empty() {
+			(T::State::new(), kind(StateRestoreKind::New))
+		} else if envelope.version > T::VERSION {
+			(T::State::new(), kind(StateRestoreKind::ResetNewer))
+		} else if envelope.version == T::VERSION {
+			match T::Deserializer::deserialize_static(envelope.payload) {
+				Ok((state, noted)) => {
+					notes = noted;
+					(state, kind(StateRestoreKind::Restored))
+				}
+				Err(()) => (T::State::new(), kind(StateRestoreKind::ResetInvalid)),
 			}
 		} else {
-			Box::into_raw(Box::new(T::State::new())).cast()
+			let migrated = T::MIGRATIONS
+				.get(..T::VERSION as usize)
+				.ok_or(())
+				.and_then(|migrations| migrate(migrations, envelope.version, envelope.payload));
+			match migrated {
+				Ok(payload) => match T::Deserializer::deserialize_noted(&payload) {
+					Ok((state, noted)) => {
+						notes = noted;
+						(state, kind(StateRestoreKind::Migrated))
+					}
+					Err(()) => (T::State::new(), kind(StateRestoreKind::ResetInvalid)),
+				},
+				Err(()) => (T::State::new(), kind(StateRestoreKind::ResetMigration)),
+			}
+		};
+
+		// New state is always valid, otherwise there would be no state to fall back to.
+		let restored = matches!(
+			report.kind,
+			StateRestoreKind::Restored | StateRestoreKind::Migrated
+		);
+		let (state, report) = match T::validate(&state) {
+			Err(reason) if restored => {
+				notes = vec![reason];
+				(T::State::new(), kind(StateRestoreKind::Rejected))
+			}
+			_ => (state, report),
+		};
+
+		if !restore.is_null() {
+			unsafe { *restore = report };
+		}
+		for note in notes {
+			unsafe { visit(out, note.as_str().into()) };
 		}
+		Box::into_raw(Box::new(state)).cast()
 	}
 
 	fn state_drop(state_ptr: *mut c_void) {
-		unsafe { Box::from_raw(state_ptr as *mut T::State) };
+		drop(unsafe { Box::from_raw(state_ptr as *mut T::State) });
 	}
 
 	fn state_serialized_new(state_ptr: *const c_void) -> FfiArrayMut<'static, u8> {
 		let state: &T::State = unsafe { &*state_ptr.cast() };
-		T::Serializer::serialize(state).unwrap_or(FfiArrayMut::<u8>::empty())
+		serialize_state::<T>(state)
+			.map(|bytes| unsafe { FfiArrayMut::unsafe_from(bytes) })
+			.unwrap_or(FfiArrayMut::<u8>::empty())
 	}
 
 	fn state_serialized_drop(serialized: FfiArrayMut<'static, u8>) {
-		let vec: Vec<u8> = unsafe { serialized.unsafe_into() };
-		std::mem::drop(vec)
+		// Empty arrays are not necessarily backed by an allocation.
+		if !serialized.is_empty() {
+			let vec: Vec<u8> = unsafe { serialized.unsafe_into() };
+			std::mem::drop(vec)
+		}
 	}
 }
 
@@ -156,6 +524,39 @@ pub const ENTRY_STATE_SERIALIZE_NEW: &str = "hotbolt_entry_state_serialize_new";
 ///  See [`FfiState::state_serialized_drop`](FfiState::state_serialized_drop).
 pub const ENTRY_STATE_SERIALIZE_DROP: &str = "hotbolt_entry_state_serialize_drop";
 
+/// Low level version of [`StateLayout`](StateLayout).
+pub trait FfiStateLayout {
+	/// Returns the fingerprint of the state layout. State is reused across soft reloads when the
+	/// fingerprints of both libraries match.
+	fn state_layout() -> u64;
+}
+
+impl<T: StateConverter> FfiStateLayout for T
+where
+	T::State: StateLayout,
+{
+	fn state_layout() -> u64 {
+		<T::State as StateLayout>::LAYOUT
+	}
+}
+
+/// See [`FfiStateLayout::state_layout`](FfiStateLayout::state_layout). Optional.
+pub const ENTRY_STATE_LAYOUT: &str = "hotbolt_entry_state_layout";
+
+/// Renders the state as JSON. Optional, only available for serde-backed states.
+///
+/// The result is dropped using [`ENTRY_STATE_SERIALIZE_DROP`](ENTRY_STATE_SERIALIZE_DROP).
+///
+/// Signature: `(state_ptr: *const c_void) -> FfiArrayMut<'static, u8>`
+pub const ENTRY_STATE_JSON_NEW: &str = "hotbolt_entry_state_json_new";
+
+/// Serializes state from JSON. Optional, only available for serde-backed states.
+///
+/// The result is dropped using [`ENTRY_STATE_SERIALIZE_DROP`](ENTRY_STATE_SERIALIZE_DROP).
+///
+/// Signature: `(json: FfiArray<u8>, out: *mut c_void, visit: FfiStringVisitor) -> FfiArrayMut<u8>`
+pub const ENTRY_STATE_JSON_PARSE: &str = "hotbolt_entry_state_json_parse";
+
 /// Low level version of [`App`](App).
 pub trait FfiApp {
 	/// Creates a app. The app consists of mostly static code that is rarely changed.
@@ -172,8 +573,17 @@ impl<T: App> FfiApp for T {
 	}
 
 	fn app_drop(app_ptr: *mut c_void) {
-		unsafe { Box::from_raw(app_ptr as *mut T) };
+		drop(unsafe { Box::from_raw(app_ptr as *mut T) });
+	}
+}
+
+// Runs without an app are passed a placeholder, which is never read.
+impl FfiApp for () {
+	fn app_new() -> *mut c_void {
+		NonNull::<()>::dangling().as_ptr() as *mut c_void
 	}
+
+	fn app_drop(_app_ptr: *mut c_void) {}
 }
 
 /// See [`FfiApp::app_new`](FfiApp::app_new).
@@ -201,8 +611,87 @@ impl<T: AppVersion<T = str>> FfiAppVersion for T {
 	}
 }
 
+// Runs without an app version are only compatible with each other.
+impl FfiAppVersion for () {
+	fn app_version() -> FfiArray<'static, u8> {
+		"".into()
+	}
+
+	fn app_compatible(other: FfiArray<u8>) -> bool {
+		other.is_empty()
+	}
+}
+
 /// See [`FfiAppVersion::app_version`](FfiAppVersion::app_version).
 pub const ENTRY_APP_VERSION: &str = "hotbolt_entry_app_version";
 
 /// See [`FfiAppVersion::app_compatible`](FfiAppVersion::app_compatible).
 pub const ENTRY_APP_COMPATIBLE: &str = "hotbolt_entry_app_compatible";
+
+/// Table of [`reloadable`](crate::reload) functions, owned by the runner.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FfiReloadTable {
+	pub table: *const c_void,
+	/// Returns the entry of the function exported under `symbol`, adding it with `function` when
+	/// missing. The entry always points to the latest build of the function.
+	pub entry: unsafe extern "C" fn(
+		table: *const c_void,
+		symbol: FfiArray<'_, u8>,
+		function: *const c_void,
+	) -> *const AtomicPtr<c_void>,
+}
+
+/// Binds the library to the table of reloadable functions. Optional.
+///
+/// Exported by each entry, and erased along with it.
+///
+/// Signature: `(table: *const FfiReloadTable)`
+pub const ENTRY_RELOADABLE_BIND: &str = "hotbolt_entry_reloadable_bind";
+
+/// Prefix of the symbols of reloadable functions, followed by their name and the fingerprint of
+/// their signature.
+pub const ENTRY_RELOADABLE_PREFIX: &str = "hotbolt_reloadable_";
+
+/// Callback used to copy bytes out of the library without transferring ownership.
+pub type FfiBytesVisitor = unsafe extern "C" fn(out: *mut c_void, value: FfiArray<'_, u8>);
+
+/// Saves a [`persistent`](crate::persistent) static by visiting its encoded value. Statics that
+/// were not initialized yet visit nothing.
+pub type FfiPersistentSave =
+	unsafe extern "C" fn(value: *const c_void, out: *mut c_void, visit: FfiBytesVisitor);
+
+/// Store of the [`persistent`](crate::persistent) statics, owned by the runner.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FfiPersistentStore {
+	pub store: *const c_void,
+	/// Registers the static `value` under `name`, then visits its stored value if there is one.
+	/// The runner saves the static through `save` until the library is unloaded or soft reloaded.
+	pub register: unsafe extern "C" fn(
+		store: *const c_void,
+		name: FfiArray<'_, u8>,
+		value: *const c_void,
+		save: FfiPersistentSave,
+		out: *mut c_void,
+		visit: FfiBytesVisitor,
+	),
+}
+
+/// Binds the library to the store of persistent statics. Optional.
+///
+/// Exported by each entry, and erased along with it.
+///
+/// Signature: `(store: *const FfiPersistentStore)`
+pub const ENTRY_PERSISTENT_BIND: &str = "hotbolt_entry_persistent_bind";
+
+/// Called after the library is loaded, before any entry point runs. Optional.
+///
+/// Signature: `(reason: LifecycleReason)`
+pub const ENTRY_ON_LOAD: &str = "hotbolt_entry_on_load";
+
+/// Called before the library is unloaded, while the previous entry point may still be running.
+/// Optional.
+///
+/// Signature: `(reason: LifecycleReason)`
+pub const ENTRY_ON_UNLOAD: &str = "hotbolt_entry_on_unload";
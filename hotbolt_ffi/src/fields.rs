@@ -0,0 +1,437 @@
+//! Field-keyed encoding used by `#[derive(HotboltState)]`.
+//!
+//! Structs are encoded as a list of named fields, each value prefixed with its length. Fields are
+//! looked up by name when decoding, so adding, removing and reordering fields keeps the remaining
+//! fields intact.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	convert::TryInto,
+	hash::Hash,
+	marker::PhantomData,
+	sync::{
+		atomic::{
+			AtomicBool,
+			AtomicI16,
+			AtomicI32,
+			AtomicI64,
+			AtomicI8,
+			AtomicIsize,
+			AtomicU16,
+			AtomicU32,
+			AtomicU64,
+			AtomicU8,
+			AtomicUsize,
+			Ordering,
+		},
+		Mutex,
+		RwLock,
+	},
+};
+
+use crate::{
+	base::State,
+	common::{Deserializer, FfiArrayMut, Serializer},
+	prelude::UnsafeFrom,
+};
+
+/// Value that can be stored in a field of a struct deriving `HotboltState`.
+pub trait FieldValue: Sized {
+	/// Appends the encoded value to `bytes`.
+	fn encode(&self, bytes: &mut Vec<u8>);
+
+	/// Decodes a value previously encoded with [`encode`](FieldValue::encode).
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()>;
+}
+
+/// Collects the fields that did not decode as-is.
+#[derive(Debug, Default)]
+pub struct FieldReport {
+	path: Vec<&'static str>,
+	notes: Vec<String>,
+}
+
+impl FieldReport {
+	fn qualified(&self, name: &str) -> String {
+		let mut path = self.path.join(".");
+		if !path.is_empty() {
+			path.push('.');
+		}
+		path.push_str(name);
+		path
+	}
+
+	fn note(&mut self, note: String) {
+		self.notes.push(note);
+	}
+
+	/// Returns a line for each field that was defaulted, dropped or renamed.
+	pub fn into_notes(self) -> Vec<String> {
+		self.notes
+	}
+}
+
+/// Reads a `u32` length prefixed slice, advancing `bytes` past it.
+pub fn read_framed<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8], ()> {
+	let len = u32::decode(bytes.get(..4).ok_or(())?, &mut FieldReport::default())? as usize;
+	let framed = bytes.get(4..4 + len).ok_or(())?;
+	*bytes = &bytes[4 + len..];
+	Ok(framed)
+}
+
+/// Writes a `u32` length prefixed slice.
+pub fn write_framed(bytes: &mut Vec<u8>, framed: &[u8]) {
+	(framed.len() as u32).encode(bytes);
+	bytes.extend_from_slice(framed);
+}
+
+/// Encodes the value as a length prefixed slice.
+fn encode_framed<T: FieldValue>(bytes: &mut Vec<u8>, value: &T) {
+	let mut framed = Vec::new();
+	value.encode(&mut framed);
+	write_framed(bytes, &framed);
+}
+
+/// Fields of an encoded struct, used by the derived [`FieldValue::decode`](FieldValue::decode).
+pub struct FieldsDecoder<'a> {
+	fields: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> FieldsDecoder<'a> {
+	pub fn new(mut bytes: &'a [u8]) -> Result<Self, ()> {
+		let mut fields = Vec::new();
+		while !bytes.is_empty() {
+			let name = std::str::from_utf8(read_framed(&mut bytes)?).map_err(|_| ())?;
+			fields.push((name, read_framed(&mut bytes)?));
+		}
+		Ok(Self { fields })
+	}
+
+	/// Decodes the field with the given name, falling back to its aliases and then a new value.
+	pub fn field<T: FieldValue + State>(
+		&mut self,
+		name: &'static str,
+		aliases: &[&str],
+		report: &mut FieldReport,
+	) -> T {
+		let position = std::iter::once(name)
+			.chain(aliases.iter().copied())
+			.find_map(|key| self.fields.iter().position(|(field, _)| *field == key));
+		let (key, bytes) = match position {
+			Some(position) => self.fields.remove(position),
+			None => {
+				report.note(format!("Defaulted new field `{}`", report.qualified(name)));
+				return T::new();
+			}
+		};
+
+		report.path.push(name);
+		let value = T::decode(bytes, report);
+		report.path.pop();
+		match value {
+			Ok(value) => {
+				if key != name {
+					let old = report.qualified(key);
+					report.note(format!("Renamed field `{}` to `{}`", old, report.qualified(name)));
+				}
+				value
+			}
+			Err(()) => {
+				report.note(format!("Defaulted invalid field `{}`", report.qualified(name)));
+				T::new()
+			}
+		}
+	}
+
+	/// Reports the fields that were not decoded as dropped.
+	pub fn finish(self, report: &mut FieldReport) {
+		for (name, _) in self.fields {
+			report.note(format!("Dropped removed field `{}`", report.qualified(name)));
+		}
+	}
+}
+
+/// Encodes a named field, used by the derived [`FieldValue::encode`](FieldValue::encode).
+pub fn encode_field<T: FieldValue>(bytes: &mut Vec<u8>, name: &str, value: &T) {
+	write_framed(bytes, name.as_bytes());
+	encode_framed(bytes, value);
+}
+
+/// Converts a [`FieldValue`](FieldValue) to and from bytes.
+pub struct FieldConverter<T> {
+	phantom: PhantomData<T>,
+}
+
+impl<T: FieldValue> Serializer<T> for FieldConverter<T> {
+	fn serialize(value: &T) -> Result<FfiArrayMut<'static, u8>, ()> {
+		let mut bytes = Vec::new();
+		value.encode(&mut bytes);
+		Ok(unsafe { FfiArrayMut::unsafe_from(bytes) })
+	}
+}
+
+impl<T: FieldValue> Deserializer<T> for FieldConverter<T> {
+	fn deserialize(bytes: &[u8]) -> Result<T, ()> {
+		T::decode(bytes, &mut FieldReport::default())
+	}
+
+	fn deserialize_noted(bytes: &[u8]) -> Result<(T, Vec<String>), ()> {
+		let mut report = FieldReport::default();
+		let value = T::decode(bytes, &mut report)?;
+		Ok((value, report.into_notes()))
+	}
+}
+
+macro_rules! FieldValueNumber {
+	($($t:ty),*) => {
+		$(
+			impl FieldValue for $t {
+				fn encode(&self, bytes: &mut Vec<u8>) {
+					bytes.extend_from_slice(&self.to_le_bytes());
+				}
+
+				fn decode(bytes: &[u8], _: &mut FieldReport) -> Result<Self, ()> {
+					bytes.try_into().map(Self::from_le_bytes).map_err(|_| ())
+				}
+			}
+		)*
+	};
+}
+
+FieldValueNumber!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl FieldValue for usize {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		(*self as u64).encode(bytes);
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		u64::decode(bytes, report)?.try_into().map_err(|_| ())
+	}
+}
+
+impl FieldValue for isize {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		(*self as i64).encode(bytes);
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		i64::decode(bytes, report)?.try_into().map_err(|_| ())
+	}
+}
+
+impl FieldValue for bool {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		bytes.push(*self as u8);
+	}
+
+	fn decode(bytes: &[u8], _: &mut FieldReport) -> Result<Self, ()> {
+		match bytes {
+			[0] => Ok(false),
+			[1] => Ok(true),
+			_ => Err(()),
+		}
+	}
+}
+
+impl FieldValue for char {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		(*self as u32).encode(bytes);
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		char::from_u32(u32::decode(bytes, report)?).ok_or(())
+	}
+}
+
+impl FieldValue for String {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		bytes.extend_from_slice(self.as_bytes());
+	}
+
+	fn decode(bytes: &[u8], _: &mut FieldReport) -> Result<Self, ()> {
+		String::from_utf8(bytes.to_vec()).map_err(|_| ())
+	}
+}
+
+impl FieldValue for () {
+	fn encode(&self, _: &mut Vec<u8>) {}
+
+	fn decode(bytes: &[u8], _: &mut FieldReport) -> Result<Self, ()> {
+		if bytes.is_empty() {
+			Ok(())
+		} else {
+			Err(())
+		}
+	}
+}
+
+impl<T: FieldValue> FieldValue for Option<T> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		if let Some(value) = self {
+			bytes.push(1);
+			value.encode(bytes);
+		} else {
+			bytes.push(0);
+		}
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		match bytes.split_first() {
+			Some((0, [])) => Ok(None),
+			Some((1, value)) => T::decode(value, report).map(Some),
+			_ => Err(()),
+		}
+	}
+}
+
+impl<T: FieldValue> FieldValue for Box<T> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		self.as_ref().encode(bytes);
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		T::decode(bytes, report).map(Box::new)
+	}
+}
+
+/// Encodes each item as a length prefixed slice.
+fn encode_items<'a, T: FieldValue + 'a>(bytes: &mut Vec<u8>, items: impl Iterator<Item = &'a T>) {
+	for item in items {
+		encode_framed(bytes, item);
+	}
+}
+
+/// Decodes the length prefixed items written by [`encode_items`](encode_items).
+fn decode_items<T: FieldValue>(
+	mut bytes: &[u8],
+	report: &mut FieldReport,
+) -> Result<Vec<T>, ()> {
+	let mut items = Vec::new();
+	while !bytes.is_empty() {
+		items.push(T::decode(read_framed(&mut bytes)?, report)?);
+	}
+	Ok(items)
+}
+
+impl<T: FieldValue> FieldValue for Vec<T> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_items(bytes, self.iter());
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		decode_items(bytes, report)
+	}
+}
+
+impl<T: FieldValue, const N: usize> FieldValue for [T; N] {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_items(bytes, self.iter());
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		decode_items(bytes, report)?.try_into().map_err(|_| ())
+	}
+}
+
+impl<A: FieldValue, B: FieldValue> FieldValue for (A, B) {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_framed(bytes, &self.0);
+		encode_framed(bytes, &self.1);
+	}
+
+	fn decode(mut bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		let a = A::decode(read_framed(&mut bytes)?, report)?;
+		let b = B::decode(read_framed(&mut bytes)?, report)?;
+		if bytes.is_empty() {
+			Ok((a, b))
+		} else {
+			Err(())
+		}
+	}
+}
+
+/// Encodes each key and value pair like a list of tuples.
+fn encode_pairs<'a, K: FieldValue + 'a, V: FieldValue + 'a>(
+	bytes: &mut Vec<u8>,
+	pairs: impl Iterator<Item = (&'a K, &'a V)>,
+) {
+	for (key, value) in pairs {
+		let mut pair = Vec::new();
+		encode_framed(&mut pair, key);
+		encode_framed(&mut pair, value);
+		write_framed(bytes, &pair);
+	}
+}
+
+impl<K: FieldValue + Eq + Hash, V: FieldValue> FieldValue for HashMap<K, V> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_pairs(bytes, self.iter());
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		Ok(decode_items::<(K, V)>(bytes, report)?.into_iter().collect())
+	}
+}
+
+impl<K: FieldValue + Ord, V: FieldValue> FieldValue for BTreeMap<K, V> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		encode_pairs(bytes, self.iter());
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		Ok(decode_items::<(K, V)>(bytes, report)?.into_iter().collect())
+	}
+}
+
+// Atomics and locks are encoded as their value, so that they can back persistent statics.
+macro_rules! FieldValueAtomic {
+	($($atomic:ty: $t:ty),*) => {
+		$(
+			impl FieldValue for $atomic {
+				fn encode(&self, bytes: &mut Vec<u8>) {
+					self.load(Ordering::Relaxed).encode(bytes);
+				}
+
+				fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+					<$t>::decode(bytes, report).map(Self::new)
+				}
+			}
+		)*
+	};
+}
+
+FieldValueAtomic!(
+	AtomicBool: bool,
+	AtomicU8: u8,
+	AtomicU16: u16,
+	AtomicU32: u32,
+	AtomicU64: u64,
+	AtomicUsize: usize,
+	AtomicI8: i8,
+	AtomicI16: i16,
+	AtomicI32: i32,
+	AtomicI64: i64,
+	AtomicIsize: isize
+);
+
+// A poisoned lock still holds the last value written to it.
+impl<T: FieldValue> FieldValue for Mutex<T> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		self.lock().unwrap_or_else(|e| e.into_inner()).encode(bytes);
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		T::decode(bytes, report).map(Mutex::new)
+	}
+}
+
+impl<T: FieldValue> FieldValue for RwLock<T> {
+	fn encode(&self, bytes: &mut Vec<u8>) {
+		self.read().unwrap_or_else(|e| e.into_inner()).encode(bytes);
+	}
+
+	fn decode(bytes: &[u8], report: &mut FieldReport) -> Result<Self, ()> {
+		T::decode(bytes, report).map(RwLock::new)
+	}
+}
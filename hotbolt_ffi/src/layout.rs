@@ -0,0 +1,130 @@
+//! Fingerprints of the memory layout of state types.
+//!
+//! On a soft reload, state whose fingerprint is unchanged is handed to the new library as-is
+//! instead of being serialized and deserialized.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	mem,
+};
+
+/// Compile-time hash of a type layout, built with `const` methods.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+	// FNV-1a, which is simple enough to evaluate in a `const` context.
+	const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+	const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+	/// Starts a fingerprint for the type with the given name.
+	pub const fn new(name: &str) -> Self {
+		Self(Self::OFFSET).str(name)
+	}
+
+	/// Starts a fingerprint for `T` with the given name, including its size and alignment.
+	pub const fn of<T>(name: &str) -> Self {
+		Self::new(name)
+			.usize(mem::size_of::<T>())
+			.usize(mem::align_of::<T>())
+	}
+
+	pub const fn bytes(self, bytes: &[u8]) -> Self {
+		let mut hash = self.0;
+		let mut index = 0;
+		while index < bytes.len() {
+			hash ^= bytes[index] as u64;
+			hash = hash.wrapping_mul(Self::PRIME);
+			index += 1;
+		}
+		Self(hash)
+	}
+
+	pub const fn str(self, value: &str) -> Self {
+		// Prefixed with the length, so that consecutive strings cannot run into each other.
+		self.usize(value.len()).bytes(value.as_bytes())
+	}
+
+	pub const fn u64(self, value: u64) -> Self {
+		self.bytes(&value.to_le_bytes())
+	}
+
+	pub const fn usize(self, value: usize) -> Self {
+		self.u64(value as u64)
+	}
+
+	/// Adds a field with its name, type, offset and the layout of its type.
+	pub const fn field(self, name: &str, type_name: &str, offset: usize, layout: u64) -> Self {
+		self.str(name).str(type_name).usize(offset).u64(layout)
+	}
+
+	pub const fn finish(self) -> u64 {
+		self.0
+	}
+}
+
+/// State types whose values can be moved to another build of the library, as long as the
+/// layout is unchanged.
+///
+/// # Safety
+///
+/// The type must be plain data, owning everything it points to, and [`LAYOUT`](StateLayout::LAYOUT)
+/// must change whenever its memory layout does. References, function pointers and trait objects
+/// would keep pointing at the code and statics of the previous build, which stay loaded but are
+/// outdated.
+pub unsafe trait StateLayout {
+	/// The fingerprint of the memory layout of the type, see [`Fingerprint`](Fingerprint).
+	const LAYOUT: u64;
+}
+
+macro_rules! StateLayoutPrimitive {
+	($($t:ty),*) => {
+		$(
+			unsafe impl StateLayout for $t {
+				const LAYOUT: u64 = Fingerprint::of::<$t>(stringify!($t)).finish();
+			}
+		)*
+	};
+}
+
+StateLayoutPrimitive!(
+	u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, (),
+	String
+);
+
+unsafe impl<T: StateLayout> StateLayout for Option<T> {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("Option").u64(T::LAYOUT).finish();
+}
+
+unsafe impl<T: StateLayout> StateLayout for Box<T> {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("Box").u64(T::LAYOUT).finish();
+}
+
+unsafe impl<T: StateLayout> StateLayout for Vec<T> {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("Vec").u64(T::LAYOUT).finish();
+}
+
+unsafe impl<T: StateLayout, const N: usize> StateLayout for [T; N] {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("Array").u64(T::LAYOUT).finish();
+}
+
+unsafe impl<A: StateLayout, B: StateLayout> StateLayout for (A, B) {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("Tuple")
+		.u64(A::LAYOUT)
+		.u64(B::LAYOUT)
+		.finish();
+}
+
+unsafe impl<K: StateLayout, V: StateLayout> StateLayout for HashMap<K, V> {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("HashMap")
+		.u64(K::LAYOUT)
+		.u64(V::LAYOUT)
+		.finish();
+}
+
+unsafe impl<K: StateLayout, V: StateLayout> StateLayout for BTreeMap<K, V> {
+	const LAYOUT: u64 = Fingerprint::of::<Self>("BTreeMap")
+		.u64(K::LAYOUT)
+		.u64(V::LAYOUT)
+		.finish();
+}
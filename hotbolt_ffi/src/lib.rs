@@ -1,15 +1,30 @@
+#![allow(clippy::missing_safety_doc, clippy::result_unit_err)]
+
 use std::{
 	ffi::c_void,
 	io::{Read, Write},
 	os::raw::c_char,
 };
 
+pub mod archive;
 pub mod base;
 mod common;
 mod convert;
+#[cfg(feature = "serde")]
+pub mod converter;
 pub mod ffi;
-
+pub mod fields;
+pub mod layout;
+pub mod persistent;
+pub mod reload;
+#[cfg(feature = "tokio")]
+pub mod runtime;
+pub mod standalone;
+
+pub use base::BuildStatus;
 pub use common::*;
+pub use standalone::run_standalone;
+use ffi::FfiBuildStatus;
 
 pub mod prelude {
 	pub use crate::convert::*;
@@ -66,8 +81,27 @@ pub trait Server {
 
 	/// Restarts the application by exiting and calling its initiliazer with an given state.
 	fn reload_with(&self, state: &[u8]);
+
+	/// Watches the given file or directory for changes without restarting the application.
+	///
+	/// Changed files are retrieved using [`asset_changed`](Server::asset_changed). Does nothing by
+	/// default.
+	fn watch(&self, _path: &str) {}
+
+	/// Returns the next changed file under one of the paths given to [`watch`](Server::watch).
+	fn asset_changed(&self) -> Option<String> {
+		None
+	}
+
+	/// Returns the status of the latest build. The current code keeps running when a build fails.
+	fn build_status(&self) -> BuildStatus {
+		BuildStatus::Ok
+	}
 }
 
+/// Callback used to copy a string out of the server without transferring ownership.
+pub type FfiStringVisitor = unsafe extern "C" fn(out: *mut c_void, value: SizedCharArray);
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct FfiServer {
@@ -75,6 +109,14 @@ pub struct FfiServer {
 	pub restart: unsafe extern "C" fn(*const c_void),
 	pub reload: unsafe extern "C" fn(*const c_void),
 	pub reload_with: unsafe extern "C" fn(*const c_void, state: SizedCharArray),
+	pub watch: unsafe extern "C" fn(*const c_void, path: SizedCharArray),
+	pub asset_changed:
+		unsafe extern "C" fn(*const c_void, out: *mut c_void, visit: FfiStringVisitor),
+	pub build_status: unsafe extern "C" fn(
+		*const c_void,
+		out: *mut c_void,
+		visit: FfiStringVisitor,
+	) -> FfiBuildStatus,
 }
 
 impl FfiServer {
@@ -100,15 +142,58 @@ impl FfiServer {
 			server.reload_with(state.as_u8_slice());
 		}
 
+		unsafe extern "C" fn server_ffi_watch<T: Server>(arg: *const c_void, path: SizedCharArray) {
+			let pointer: *const T = arg as *const T;
+			let server: &dyn Server = &*pointer;
+			server.watch(&String::from_utf8_lossy(path.as_u8_slice()));
+		}
+
+		unsafe extern "C" fn server_ffi_asset_changed<T: Server>(
+			arg: *const c_void,
+			out: *mut c_void,
+			visit: FfiStringVisitor,
+		) {
+			let pointer: *const T = arg as *const T;
+			let server: &dyn Server = &*pointer;
+			if let Some(path) = server.asset_changed() {
+				visit(out, SizedCharArray::from_slice(path.as_bytes()));
+			}
+		}
+
+		unsafe extern "C" fn server_ffi_build_status<T: Server>(
+			arg: *const c_void,
+			out: *mut c_void,
+			visit: FfiStringVisitor,
+		) -> FfiBuildStatus {
+			let pointer: *const T = arg as *const T;
+			let server: &dyn Server = &*pointer;
+			match server.build_status() {
+				BuildStatus::Ok => FfiBuildStatus::Ok,
+				BuildStatus::Building => FfiBuildStatus::Building,
+				BuildStatus::Failed(summary) => {
+					visit(out, SizedCharArray::from_slice(summary.as_bytes()));
+					FfiBuildStatus::Failed
+				}
+			}
+		}
+
 		Self {
 			server: server as *const T as *const c_void,
 			restart: server_ffi_restart::<T>,
 			reload: server_ffi_reload::<T>,
 			reload_with: server_ffi_reload_with::<T>,
+			watch: server_ffi_watch::<T>,
+			asset_changed: server_ffi_asset_changed::<T>,
+			build_status: server_ffi_build_status::<T>,
 		}
 	}
 }
 
+unsafe extern "C" fn visit_string(out: *mut c_void, value: SizedCharArray) {
+	let out = &mut *(out as *mut Option<String>);
+	*out = Some(String::from_utf8_lossy(value.as_u8_slice()).into_owned());
+}
+
 impl Server for FfiServer {
 	fn restart(&self) {
 		unsafe { (self.restart)(self.server) }
@@ -117,7 +202,21 @@ impl Server for FfiServer {
 		unsafe { (self.reload)(self.server) }
 	}
 	fn reload_with(&self, state: &[u8]) {
-		unsafe { (self.reload_with)(self.server, SizedCharArray::from_slice(&state)) }
+		unsafe { (self.reload_with)(self.server, SizedCharArray::from_slice(state)) }
+	}
+	fn watch(&self, path: &str) {
+		unsafe { (self.watch)(self.server, SizedCharArray::from_slice(path.as_bytes())) }
+	}
+	fn asset_changed(&self) -> Option<String> {
+		let mut path: Option<String> = None;
+		let out = &mut path as *mut Option<String> as *mut c_void;
+		unsafe { (self.asset_changed)(self.server, out, visit_string) };
+		path
+	}
+	fn build_status(&self) -> BuildStatus {
+		let mut summary: Option<String> = None;
+		let out = &mut summary as *mut Option<String> as *mut c_void;
+		unsafe { (self.build_status)(self.server, out, visit_string) }.with_summary(summary)
 	}
 }
 
@@ -151,6 +250,7 @@ pub trait Client {
 	fn new() -> Self;
 
 	/// The main entry point for the application.
+	#[allow(clippy::borrowed_box)]
 	fn run(&mut self, server: impl Server, state: &Box<Self::T>);
 
 	/// Returns the current application state.
@@ -179,17 +279,15 @@ impl<T: Client> FfiClient for T {
 	}
 
 	fn client_drop(client_ptr: *mut c_void) {
-		unsafe { Box::from_raw(client_ptr as *mut T) };
+		drop(unsafe { Box::from_raw(client_ptr as *mut T) });
 	}
 
 	fn client_version() -> SizedCharArray {
 		let version = T::Compatibility::version();
-		let char_array = SizedCharArray {
+		SizedCharArray {
 			array: version.as_ptr() as *const c_char,
 			len: version.len(),
-		};
-		std::mem::forget(version);
-		char_array
+		}
 	}
 
 	fn client_compatible(other: SizedCharArray) -> bool {
@@ -198,7 +296,7 @@ impl<T: Client> FfiClient for T {
 	}
 
 	fn state_new(state_serialized: SizedCharArray) -> *const c_void {
-		if let Some(state) = T::Serializer::deserialize(state_serialized.as_u8_slice()).ok() {
+		if let Ok(state) = T::Serializer::deserialize(state_serialized.as_u8_slice()) {
 			let state_ptr = Box::new(state);
 			Box::into_raw(state_ptr) as *const c_void
 		} else {
@@ -207,7 +305,7 @@ impl<T: Client> FfiClient for T {
 	}
 
 	fn state_drop(state_ptr: *mut c_void) {
-		unsafe { Box::from_raw(state_ptr as *mut T::T) };
+		drop(unsafe { Box::from_raw(state_ptr as *mut T::T) });
 	}
 
 	fn state_serialized(state_ptr: *const c_void) -> SizedCharArray {
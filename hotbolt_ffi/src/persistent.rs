@@ -0,0 +1,124 @@
+//! Statics keeping their value across hard and soft reloads.
+//!
+//! Statics declared with [`persistent!`](crate::persistent!) register with a store owned by the
+//! runner the first time they are used. The runner saves them along with the state and each new
+//! build restores them by name, so they do not need to be part of the state.
+
+use std::{
+	ffi::c_void,
+	ops::Deref,
+	ptr,
+	sync::{
+		atomic::{AtomicPtr, Ordering},
+		OnceLock,
+	},
+};
+
+use crate::{
+	ffi::{FfiBytesVisitor, FfiPersistentStore},
+	fields::{FieldReport, FieldValue},
+	FfiArray,
+};
+
+static STORE: AtomicPtr<FfiPersistentStore> = AtomicPtr::new(ptr::null_mut());
+
+/// Binds the library to the runner's store, exported by the main macros under
+/// [`ENTRY_PERSISTENT_BIND`](crate::ffi::ENTRY_PERSISTENT_BIND).
+///
+/// # Safety
+///
+/// `store` must be null or outlive every use of a persistent static.
+pub unsafe fn bind(store: *const FfiPersistentStore) {
+	STORE.store(store as *mut FfiPersistentStore, Ordering::Release);
+}
+
+/// Declares statics that keep their value across reloads.
+///
+/// The value must implement [`FieldValue`](crate::fields::FieldValue), such as atomics, mutexes
+/// and types deriving `HotboltState`. Statics are stored under their path and type, so renaming
+/// a static or changing its type starts it over from its initial value.
+#[macro_export]
+macro_rules! persistent {
+	($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr;)*) => {
+		$(
+			$(#[$attr])*
+			$vis static $name: $crate::persistent::Persistent<$ty> =
+				$crate::persistent::Persistent::new(
+					concat!(module_path!(), "::", stringify!($name), ": ", stringify!($ty)),
+					|| $init,
+				);
+		)*
+	};
+}
+
+/// Static declared with [`persistent!`](crate::persistent!), restored on first use.
+pub struct Persistent<T> {
+	name: &'static str,
+	init: fn() -> T,
+	value: OnceLock<T>,
+}
+
+impl<T> Persistent<T> {
+	pub const fn new(name: &'static str, init: fn() -> T) -> Self {
+		Self {
+			name,
+			init,
+			value: OnceLock::new(),
+		}
+	}
+}
+
+impl<T: FieldValue> Persistent<T> {
+	/// Registers the static and returns its stored value, or its initial value when it has none.
+	///
+	/// Falls back to the initial value when the library is not bound to a store, such as when it
+	/// is not run by the hotbolt runner.
+	fn load(&self) -> T {
+		let store = STORE.load(Ordering::Acquire);
+		if store.is_null() {
+			return (self.init)();
+		}
+		let store = unsafe { &*store };
+		let mut stored: Option<Vec<u8>> = None;
+		unsafe {
+			(store.register)(
+				store.store,
+				self.name.into(),
+				self as *const Self as *const c_void,
+				save::<T>,
+				&mut stored as *mut Option<Vec<u8>> as *mut c_void,
+				visit_bytes,
+			)
+		};
+		// Values that no longer decode, such as after changing their encoding, start over.
+		stored
+			.and_then(|bytes| T::decode(&bytes, &mut FieldReport::default()).ok())
+			.unwrap_or_else(self.init)
+	}
+}
+
+impl<T: FieldValue> Deref for Persistent<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		self.value.get_or_init(|| self.load())
+	}
+}
+
+unsafe extern "C" fn save<T: FieldValue>(
+	value: *const c_void,
+	out: *mut c_void,
+	visit: FfiBytesVisitor,
+) {
+	let persistent = &*(value as *const Persistent<T>);
+	if let Some(value) = persistent.value.get() {
+		let mut bytes = Vec::new();
+		value.encode(&mut bytes);
+		visit(out, FfiArray::from(&bytes));
+	}
+}
+
+unsafe extern "C" fn visit_bytes(out: *mut c_void, value: FfiArray<'_, u8>) {
+	let out = &mut *(out as *mut Option<Vec<u8>>);
+	*out = Some(value.as_slice().to_vec());
+}
@@ -0,0 +1,58 @@
+//! Functions replaced in place when the library is soft reloaded.
+//!
+//! Calls to functions marked `#[hotbolt::reloadable]` go through a table owned by the runner,
+//! which points each function to its latest build. Code from older builds that is still running,
+//! such as threads started by the app, then calls the new build of the function.
+
+use std::{
+	ffi::c_void,
+	ptr,
+	sync::atomic::{AtomicPtr, Ordering},
+};
+
+use crate::ffi::FfiReloadTable;
+
+static TABLE: AtomicPtr<FfiReloadTable> = AtomicPtr::new(ptr::null_mut());
+
+/// Binds the library to the runner's table, exported by the main macros under
+/// [`ENTRY_RELOADABLE_BIND`](crate::ffi::ENTRY_RELOADABLE_BIND).
+///
+/// # Safety
+///
+/// `table` must be null or outlive every call to a reloadable function.
+pub unsafe fn bind(table: *const FfiReloadTable) {
+	TABLE.store(table as *mut FfiReloadTable, Ordering::Release);
+}
+
+/// Entry of a reloadable function in the runner's table, looked up on the first call.
+#[derive(Debug, Default)]
+pub struct Slot {
+	entry: AtomicPtr<AtomicPtr<c_void>>,
+}
+
+impl Slot {
+	pub const fn new() -> Self {
+		Self {
+			entry: AtomicPtr::new(ptr::null_mut()),
+		}
+	}
+
+	/// Returns the latest build of the function exported under `symbol`.
+	///
+	/// Returns `function` itself when the library is not bound to a table, such as when it is not
+	/// run by the hotbolt runner.
+	#[allow(clippy::not_unsafe_ptr_arg_deref)]
+	pub fn get(&self, symbol: &str, function: *const c_void) -> *const c_void {
+		let mut entry = self.entry.load(Ordering::Acquire);
+		if entry.is_null() {
+			let table = TABLE.load(Ordering::Acquire);
+			if table.is_null() {
+				return function;
+			}
+			let table = unsafe { &*table };
+			entry = unsafe { (table.entry)(table.table, symbol.into(), function) } as *mut _;
+			self.entry.store(entry, Ordering::Release);
+		}
+		unsafe { &*entry }.load(Ordering::Acquire)
+	}
+}
@@ -0,0 +1,158 @@
+//! Tokio runtime for async entry points, kept in the app across soft reloads.
+//!
+//! Each run drives the entry point until the runner waits for the run to return. The entry point
+//! and the tasks it spawned through [`spawn`](spawn) are then cancelled at their next `.await`,
+//! and the next run spawns them again from the new build of the library.
+
+use std::{
+	future::Future,
+	pin::Pin,
+	sync::Mutex,
+	task::{Context, Poll},
+	time::Duration,
+};
+
+use tokio::{
+	runtime::{self, Handle},
+	task::{AbortHandle, JoinHandle},
+};
+
+use crate::{
+	base::{self, App, AppVersion, ServerBase},
+	BuildStatus,
+	Server,
+};
+
+/// How often the runner is asked whether the run should return.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Tasks spawned by this build of the library, cancelled when the run returns.
+static TASKS: Mutex<Vec<AbortHandle>> = Mutex::new(Vec::new());
+
+/// Future entering the runtime each time it is polled.
+///
+/// Every build of the library has its own copy of tokio, whose context is only set by the runtime
+/// threads of the build that created the runtime. Entering it makes timers and spawning available
+/// to futures of later builds.
+struct Entered<F> {
+	handle: Handle,
+	future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Entered<F> {
+	type Output = F::Output;
+
+	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+		let _guard = self.handle.enter();
+		self.future.as_mut().poll(cx)
+	}
+}
+
+/// Spawns a task on the current runtime, which is cancelled when the run returns.
+///
+/// Tasks spawned through `tokio::spawn` instead keep running the code of the build that spawned
+/// them until they complete.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+	F: Future + Send + 'static,
+	F::Output: Send + 'static,
+{
+	let task = tokio::spawn(Entered {
+		handle: Handle::current(),
+		future: Box::pin(future),
+	});
+	TASKS.lock().unwrap().push(task.abort_handle());
+	task
+}
+
+/// App owning a multi-threaded tokio runtime.
+pub struct Runtime {
+	runtime: runtime::Runtime,
+}
+
+impl App for Runtime {
+	fn new() -> Self {
+		let runtime = runtime::Builder::new_multi_thread()
+			.enable_all()
+			.build()
+			.expect("Unable to create the tokio runtime");
+		Self { runtime }
+	}
+}
+
+// Builds using the same version of hotbolt keep the runtime.
+impl AppVersion for Runtime {
+	type T = str;
+
+	fn version() -> &'static str {
+		concat!("hotbolt::runtime::Runtime ", env!("CARGO_PKG_VERSION"))
+	}
+}
+
+impl Runtime {
+	pub fn handle(&self) -> &Handle {
+		self.runtime.handle()
+	}
+
+	/// Runs the future until it completes or [`should_return`](ServerBase::should_return), then
+	/// cancels the tasks spawned through [`spawn`](spawn).
+	pub fn run<T: ?Sized>(&self, server: &impl ServerBase<T>, future: impl Future<Output = ()>) {
+		self.runtime.block_on(async {
+			tokio::select! {
+				_ = future => {}
+				_ = returned(server) => {}
+			}
+		});
+		for task in TASKS.lock().unwrap().drain(..) {
+			task.abort();
+		}
+	}
+}
+
+async fn returned<T: ?Sized>(server: &impl ServerBase<T>) {
+	while !server.should_return() {
+		tokio::time::sleep(POLL_INTERVAL).await;
+	}
+}
+
+/// [`Server`](Server) passed to async entry points, which have no state.
+///
+/// Without state to reload with, [`reload_with`](Server::reload_with) must be given an empty state
+/// and soft restarts like [`reload`](Server::reload).
+pub struct AsyncServer<'a, S> {
+	server: &'a S,
+}
+
+impl<'a, S: base::Server<()>> AsyncServer<'a, S> {
+	pub fn new(server: &'a S) -> Self {
+		Self { server }
+	}
+}
+
+// Reloading keeps the runtime.
+impl<'a, S: base::Server<()>> Server for AsyncServer<'a, S> {
+	fn restart(&self) {
+		self.server.restart_hard();
+	}
+
+	fn reload(&self) {
+		self.server.restart_soft();
+	}
+
+	fn reload_with(&self, state: &[u8]) {
+		debug_assert!(state.is_empty(), "async entry points cannot reload with a state");
+		self.server.restart_soft();
+	}
+
+	fn watch(&self, path: &str) {
+		self.server.watch(path);
+	}
+
+	fn asset_changed(&self) -> Option<String> {
+		self.server.asset_changed()
+	}
+
+	fn build_status(&self) -> BuildStatus {
+		self.server.build_status()
+	}
+}
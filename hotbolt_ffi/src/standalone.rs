@@ -0,0 +1,113 @@
+//! Runs an application without the hotbolt runner, such as in release builds.
+
+use crate::base::{
+	BuildStatus,
+	MaybeApp,
+	Run,
+	Server,
+	ServerBase,
+	ServerEnabled,
+	State,
+	StateConverter,
+};
+
+/// Server that does nothing, used when running without the hotbolt runner.
+///
+/// [`is_server_enabled`](ServerEnabled::is_server_enabled) returns `false`, so code checking it
+/// compiles away.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StandaloneServer;
+
+impl ServerEnabled for StandaloneServer {
+	#[inline]
+	fn is_server_enabled() -> bool {
+		false
+	}
+}
+
+impl<T: ?Sized> ServerBase<T> for StandaloneServer {
+	#[inline]
+	fn restart_hard(&self) {}
+
+	#[inline]
+	fn restart_hard_with<U: AsRef<T>>(&self, _state: U) {}
+
+	#[inline]
+	fn watch(&self, _path: &str) {}
+
+	#[inline]
+	fn asset_changed(&self) -> Option<String> {
+		None
+	}
+
+	#[inline]
+	fn build_status(&self) -> BuildStatus {
+		BuildStatus::Ok
+	}
+
+	#[inline]
+	fn should_return(&self) -> bool {
+		false
+	}
+}
+
+impl<T: ?Sized> Server<T> for StandaloneServer {
+	#[inline]
+	fn restart_soft(&self) {}
+
+	#[inline]
+	fn restart_soft_with<U: AsRef<T>>(&self, _state: U) {}
+}
+
+/// Runs the application like the hotbolt runner does, starting with a new app and state.
+///
+/// [`Run::run`](Run::run) is called in a loop with the same app and state, until the application
+/// shuts down by exiting the process.
+pub fn run_standalone<T: Run>() -> ! {
+	let mut app = T::App::maybe_new();
+	let mut state = <T::StateConverter as StateConverter>::State::new();
+	loop {
+		T::run(&mut app, StandaloneServer, &mut state);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		sync::atomic::{AtomicU32, Ordering},
+		thread,
+		time::{Duration, Instant},
+	};
+
+	use super::{run_standalone, StandaloneServer};
+	use crate::base::{BaseRun, ServerBase, ServerEnabled};
+
+	static RUNS: AtomicU32 = AtomicU32::new(0);
+
+	struct Counter;
+
+	// Parks once it ran a few times, standing in for the application exiting the process.
+	impl BaseRun for Counter {
+		type StateConverter = ();
+
+		fn run(server: impl ServerBase<()>, _state: &mut ()) {
+			assert!(!server.should_return());
+			if RUNS.fetch_add(1, Ordering::Relaxed) + 1 == 3 {
+				loop {
+					thread::park();
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn runs_in_a_loop() {
+		assert!(!StandaloneServer::is_server_enabled());
+		thread::spawn(run_standalone::<Counter>);
+		let started = Instant::now();
+		while RUNS.load(Ordering::Relaxed) < 3 && started.elapsed() < Duration::from_secs(5) {
+			thread::sleep(Duration::from_millis(10));
+		}
+		assert_eq!(RUNS.load(Ordering::Relaxed), 3);
+	}
+}
@@ -1,16 +1,46 @@
 use hotbolt_ffi::ffi::{
+	entry_symbol,
 	ENTRY_APP_COMPATIBLE,
 	ENTRY_APP_DROP,
 	ENTRY_APP_NEW,
 	ENTRY_APP_RUN,
 	ENTRY_APP_VERSION,
+	ENTRY_MANIFEST,
+	ENTRY_ON_LOAD,
+	ENTRY_ON_UNLOAD,
+	ENTRY_PERSISTENT_BIND,
+	ENTRY_RELOADABLE_BIND,
+	ENTRY_RELOADABLE_PREFIX,
+	ENTRY_SERVER_VERSION,
 	ENTRY_STATE_DROP,
+	ENTRY_STATE_JSON_NEW,
+	ENTRY_STATE_JSON_PARSE,
+	ENTRY_STATE_LAYOUT,
 	ENTRY_STATE_NEW,
-	ENTRY_SERVER_VERSION,
+	ENTRY_STATE_SERIALIZE_DROP,
+	ENTRY_STATE_SERIALIZE_NEW,
 };
+use hotbolt_ffi::layout::Fingerprint;
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
-use syn::{Ident, Item};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+	punctuated::Punctuated,
+	spanned::Spanned,
+	Data,
+	DeriveInput,
+	Fields,
+	FnArg,
+	Ident,
+	Item,
+	ItemFn,
+	Lit,
+	Member,
+	Meta,
+	NestedMeta,
+	Token,
+	Type,
+	TypeParamBound,
+};
 
 // https://stackoverflow.com/questions/38088067/equivalent-of-func-or-function-in-rust
 macro_rules! function_name {
@@ -26,24 +56,110 @@ macro_rules! function_name {
 	};
 }
 
-// TODO: Maybe compile time type assertions for cleaner errors?
+/// Returns the error for an attribute used on the wrong kind of item.
+fn intended_on(name: &str, input: &Item, kind: &str) -> syn::Error {
+	let name = name.rsplit("::").next().unwrap_or(name);
+	syn::Error::new_spanned(input, format!("#[{}] is intended on {}", name, kind))
+}
+
+/// Condition under which the generated exports are erased, given with `erase_if = "..."`.
+///
+/// Defaults to `feature = "hotbolt_erase"`, but any `cfg` predicate works, such as
+/// `not(debug_assertions)`.
+struct Erase(Meta);
+
+impl Erase {
+	fn of(options: &Options) -> syn::Result<Self> {
+		let mut erase = None;
+		for option in options {
+			let value = match option {
+				NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("erase_if") => {
+					value
+				}
+				_ => continue,
+			};
+			if erase.is_some() {
+				return Err(syn::Error::new_spanned(value, "duplicate erase condition"));
+			}
+			erase = Some(match &value.lit {
+				Lit::Str(lit) => Self(lit.parse().map_err(|_err| {
+					syn::Error::new_spanned(lit, "Expected a `cfg` predicate")
+				})?),
+				lit => return Err(syn::Error::new_spanned(lit, "Expected a string")),
+			});
+		}
+		Ok(erase.unwrap_or_else(|| Self(syn::parse_quote!(feature = "hotbolt_erase"))))
+	}
+
+	/// Returns the attribute keeping an item unless erased.
+	fn keep(&self) -> proc_macro2::TokenStream {
+		let predicate = &self.0;
+		quote! { #[cfg(not(#predicate))] }
+	}
+
+	/// Returns the attribute keeping an item only when erased.
+	fn erased(&self) -> proc_macro2::TokenStream {
+		let predicate = &self.0;
+		quote! { #[cfg(#predicate)] }
+	}
+}
+
+/// Checks that every option is one of the given `key = "..."` options.
+fn check_options(options: &Options, keys: &[&str]) -> syn::Result<()> {
+	let unknown = options.iter().find(|option| match option {
+		NestedMeta::Meta(Meta::NameValue(value)) => {
+			!keys.iter().any(|key| value.path.is_ident(key))
+		}
+		_ => true,
+	});
+	match unknown {
+		Some(option) => {
+			let expected: Vec<_> = keys.iter().map(|key| format!("`{} = \"...\"`", key)).collect();
+			let message = format!("unknown option, expected {}", expected.join(" or "));
+			Err(syn::Error::new_spanned(option, message))
+		}
+		None => Ok(()),
+	}
+}
 
-fn wrap_method2<T>(name: &str, token_stream: TokenStream, function_fn: T) -> TokenStream
+/// Exports the function returned by `function_fn`, which calls the annotated function.
+///
+/// The annotated function is asserted to coerce to `signature`, so that a wrong signature is
+/// reported at the function instead of inside the export.
+fn wrap_method2<T>(
+	name: &str,
+	attr: TokenStream,
+	token_stream: TokenStream,
+	signature: proc_macro2::TokenStream,
+	function_fn: T,
+) -> TokenStream
 where
 	T: Fn(&Ident) -> proc_macro2::TokenStream,
 {
+	let options = syn::parse_macro_input!(attr with Options::parse_terminated);
 	let input: Item = syn::parse_macro_input!(token_stream);
 	let input_function = match &input {
 		Item::Fn(item) => item,
-		_ => panic!("#[{}] is intended on a function", name),
+		_ => return TokenStream::from(intended_on(name, &input, "a function").to_compile_error()),
+	};
+	let erase = match check_options(&options, &["erase_if"]).and_then(|()| Erase::of(&options)) {
+		Ok(erase) => erase.keep(),
+		Err(e) => return TokenStream::from(e.to_compile_error()),
 	};
 
-	let function = function_fn(&input_function.sig.ident);
+	let ident = &input_function.sig.ident;
+	let function = function_fn(ident);
+	let assertion = quote_spanned! {input_function.sig.span()=>
+		const _: #signature = #ident;
+	};
 	let expanded = quote! {
 		#input
 
-		#[cfg(not(feature = "hotbolt_erase"))]
-		#[no_mangle]
+		#erase
+		#assertion
+
+		#erase
+		#[unsafe(no_mangle)]
 		pub extern "C" #function
 	};
 
@@ -51,73 +167,32 @@ where
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_trait_state(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	let input: Item = syn::parse_macro_input!(token_stream);
-	if let Item::Struct(input_struct) = &input {
-		let name = &input_struct.ident;
-		let expanded = quote! {
-			type HotboltAutoState: hotbolt::ffi::FfiState = #name;
-
-			#[hotbolt::hotbolt_entry_state_new]
-			fn hotbolt_auto_state_new(serialized: hotbolt::ffi::FfiArray<'static, u8>) -> *mut c_void {
-				HotboltAutoState::state_new(serialized)
-			}
-
-			#[hotbolt::hotbolt_entry_state_drop]
-			fn hotbolt_auto_state_drop(state_ptr: *mut c_void) {
-				HotboltAutoState::state_drop(state_ptr)
-			}
-
-			#[hotbolt::hotbolt_entry_state_serialize_new]
-			fn hotbolt_auto_state_serialized(state_ptr: *const c_void) -> hotbolt::ffi::FfiArrayMut<'static, u8> {
-				HotboltAutoState::state_serialize_new(state_ptr)
-			}
-
-			#[hotbolt::hotbolt_entry_state_serialize_drop]
-			fn hotbolt_auto_state_serialized(serialized: hotbolt::ffi::FfiArrayMut<'static, u8>) {
-				HotboltAutoState::state_serialize_drop(serialized)
-			}
-		};
-
-		TokenStream::from(expanded)
-	} else {
-		panic!("#[hotbolt_trait_state] is intended on a struct");
-	}
-}
-
-#[proc_macro_attribute]
-pub fn hotbolt_trait_entry(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	let input: Item = syn::parse_macro_input!(token_stream);
-	if let Item::Struct(input_struct) = &input {
-		let name = &input_struct.ident;
-		let expanded = quote! {
-			type HotboltAutoEntry: hotbolt::ffi::FfiEntry = #name;
-
-			#[hotbolt::hotbolt_entry_run]
-			fn hotbolt_auto_run(app_ptr: *mut c_void, server: FfiServer, state_ptr: *mut c_void) {
-				HotboltAutoEntry::run(app_ptr, server, state_ptr)
-			}
-		};
-
-		TokenStream::from(expanded)
-	} else {
-		panic!("#[{}] is intended on a struct", function_name!());
-	}
-}
-
-#[proc_macro_attribute]
-pub fn hotbolt_entry_run(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+pub fn hotbolt_entry_run(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! {
+		fn(*mut std::ffi::c_void, hotbolt::ffi::FfiServer, *mut std::ffi::c_void)
+	};
+	// The options are checked by `wrap_method2`.
+	let options = attr.clone();
+	let options = syn::parse_macro_input!(options with Options::parse_terminated);
+	let erase = match Erase::of(&options) {
+		Ok(erase) => erase,
+		Err(e) => return TokenStream::from(e.to_compile_error()),
+	};
 	let run_method: proc_macro2::TokenStream =
-		wrap_method2(function_name!(), token_stream, |ident| {
+		wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
 			let name = format_ident!("{}", ENTRY_APP_RUN);
 			quote! {
-				fn #name(app_ptr: *mut c_void, server: FfiServer, state_ptr: *mut c_void) {
+				fn #name(
+					app_ptr: *mut std::ffi::c_void,
+					server: hotbolt::ffi::FfiServer,
+					state_ptr: *mut std::ffi::c_void,
+				) {
 					#ident(app_ptr, server, state_ptr)
 				}
 			}
 		})
 		.into();
-	let version_method = hotbolt_version();
+	let version_method = hotbolt_version(&erase);
 	let wrapper = quote! {
 		#run_method
 
@@ -127,20 +202,34 @@ pub fn hotbolt_entry_run(_attr: TokenStream, token_stream: TokenStream) -> Token
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_entry_state_new(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
+pub fn hotbolt_entry_state_new(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! {
+		fn(
+			hotbolt::FfiArray<'static, u8>,
+			*mut hotbolt::ffi::StateRestore,
+			*mut std::ffi::c_void,
+			hotbolt::ffi::FfiStringVisitor,
+		) -> *mut std::ffi::c_void
+	};
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
 		let name = format_ident!("{}", ENTRY_STATE_NEW);
 		quote! {
-			fn #name(serialized: hotbolt::ffi::FfiArray<'static, u8>) -> *mut std::ffi::c_void {
-				#ident(serialized)
+			fn #name(
+				serialized: hotbolt::FfiArray<'static, u8>,
+				restore: *mut hotbolt::ffi::StateRestore,
+				out: *mut std::ffi::c_void,
+				visit: hotbolt::ffi::FfiStringVisitor,
+			) -> *mut std::ffi::c_void {
+				#ident(serialized, restore, out, visit)
 			}
 		}
 	})
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_entry_state_drop(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
+pub fn hotbolt_entry_state_drop(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn(*mut std::ffi::c_void) };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
 		let name = format_ident!("{}", ENTRY_STATE_DROP);
 		quote! {
 			fn #name(state: *mut std::ffi::c_void) {
@@ -152,14 +241,15 @@ pub fn hotbolt_entry_state_drop(_attr: TokenStream, token_stream: TokenStream) -
 
 #[proc_macro_attribute]
 pub fn hotbolt_entry_state_serialize_new(
-	_attr: TokenStream,
+	attr: TokenStream,
 	token_stream: TokenStream,
 ) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
-		let name = format_ident!("{}", ENTRY_STATE_NEW);
+	let signature = quote! { fn(*const std::ffi::c_void) -> hotbolt::FfiArrayMut<'static, u8> };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_STATE_SERIALIZE_NEW);
 		quote! {
-			fn #name(state_ptr: *const c_void) -> hotbolt::ffi::FfiArrayMut<'static, u8> {
-				&#ident(state_ptr)
+			fn #name(state_ptr: *const std::ffi::c_void) -> hotbolt::FfiArrayMut<'static, u8> {
+				#ident(state_ptr)
 			}
 		}
 	})
@@ -167,13 +257,14 @@ pub fn hotbolt_entry_state_serialize_new(
 
 #[proc_macro_attribute]
 pub fn hotbolt_entry_state_serialize_drop(
-	_attr: TokenStream,
+	attr: TokenStream,
 	token_stream: TokenStream,
 ) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
-		let name = format_ident!("{}", ENTRY_STATE_NEW);
+	let signature = quote! { fn(hotbolt::FfiArrayMut<'static, u8>) };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_STATE_SERIALIZE_DROP);
 		quote! {
-			fn #name(serialized: FfiArrayMut<'static, u8>) {
+			fn #name(serialized: hotbolt::FfiArrayMut<'static, u8>) {
 				#ident(serialized)
 			}
 		}
@@ -181,36 +272,25 @@ pub fn hotbolt_entry_state_serialize_drop(
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_trait_app(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	let input: Item = syn::parse_macro_input!(token_stream);
-	if let Item::Struct(input_struct) = &input {
-		let name = &input_struct.ident;
-		let expanded = quote! {
-			type HotboltAutoApp: hotbolt::ffi::FfiApp = #name;
-
-			#[hotbolt::hotbolt_entry_app_new]
-			fn hotbolt_auto_app_new() -> *mut c_void {
-				HotboltAutoApp::app_new()
-			}
-
-			#[hotbolt::hotbolt_entry_app_drop]
-			fn hotbolt_auto_app_drop(app_ptr: *mut c_void) {
-				HotboltAutoApp::app_drop(app_ptr)
+pub fn hotbolt_entry_state_layout(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn() -> u64 };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_STATE_LAYOUT);
+		quote! {
+			fn #name() -> u64 {
+				#ident()
 			}
-		};
-
-		TokenStream::from(expanded)
-	} else {
-		panic!("#[{}] is intended on a struct", function_name!(),);
-	}
+		}
+	})
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_entry_app_new(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
+pub fn hotbolt_entry_app_new(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn() -> *mut std::ffi::c_void };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
 		let name = format_ident!("{}", ENTRY_APP_NEW);
 		quote! {
-			fn #name() -> *mut c_void {
+			fn #name() -> *mut std::ffi::c_void {
 				#ident()
 			}
 		}
@@ -218,11 +298,12 @@ pub fn hotbolt_entry_app_new(_attr: TokenStream, token_stream: TokenStream) -> T
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_entry_app_drop(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
+pub fn hotbolt_entry_app_drop(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn(*mut std::ffi::c_void) };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
 		let name = format_ident!("{}", ENTRY_APP_DROP);
 		quote! {
-			fn #name(app_ptr: *mut c_void) {
+			fn #name(app_ptr: *mut std::ffi::c_void) {
 				#ident(app_ptr)
 			}
 		}
@@ -230,49 +311,418 @@ pub fn hotbolt_entry_app_drop(_attr: TokenStream, token_stream: TokenStream) ->
 }
 
 #[proc_macro_attribute]
-pub fn hotbolt_trait_app_version(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+pub fn hotbolt_entry_app_version(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn() -> hotbolt::FfiArray<'static, u8> };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_APP_VERSION);
+		quote! {
+			fn #name() -> hotbolt::FfiArray<'static, u8> {
+				#ident()
+			}
+		}
+	})
+}
+
+#[proc_macro_attribute]
+pub fn hotbolt_entry_app_compatible(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn(hotbolt::FfiArray<'static, u8>) -> bool };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_APP_COMPATIBLE);
+		quote! {
+			fn #name(other: hotbolt::FfiArray<'static, u8>) -> bool {
+				#ident(other)
+			}
+		}
+	})
+}
+
+type Options = Punctuated<NestedMeta, Token![,]>;
+
+/// Returns the entry name given with `name = "..."`, see [`entry_symbol`](entry_symbol).
+fn entry_name(options: &Options) -> syn::Result<Option<String>> {
+	let mut name = None;
+	for option in options {
+		let value = match option {
+			NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("name") => value,
+			_ => continue,
+		};
+		if name.is_some() {
+			return Err(syn::Error::new_spanned(value, "duplicate entry name"));
+		}
+		let lit = match &value.lit {
+			Lit::Str(lit) => lit,
+			lit => return Err(syn::Error::new_spanned(lit, "Expected a string")),
+		};
+		// Namespaced symbols must stay valid identifiers.
+		if syn::parse_str::<Ident>(&lit.value()).is_err() {
+			return Err(syn::Error::new_spanned(lit, "entry names must be identifiers"));
+		}
+		name = Some(lit.value());
+	}
+	Ok(name)
+}
+
+/// Exports every entry point of a type implementing [`Run`](hotbolt_ffi::base::Run).
+///
+/// Optional entry points are enabled with arguments: `layout` exports the state layout for soft
+/// reloading and `json` exports the JSON conversions of serde-backed states. `name = "editor"`
+/// exports a named entry instead, chosen with `--entry editor`, so that one library can export
+/// several.
+#[proc_macro_attribute]
+pub fn main(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let options = syn::parse_macro_input!(attr with Options::parse_terminated);
 	let input: Item = syn::parse_macro_input!(token_stream);
-	if let Item::Struct(input_struct) = &input {
-		let name = &input_struct.ident;
-		let expanded = quote! {
-			type HotboltAutoAppVersion: hotbolt::ffi::FfiAppVersion = #name;
+	match hotbolt_main(&options, &input) {
+		Ok(expanded) => TokenStream::from(quote! {
+			#input
+
+			#expanded
+		}),
+		Err(e) => TokenStream::from(e.to_compile_error()),
+	}
+}
+
+fn hotbolt_main(options: &Options, input: &Item) -> syn::Result<proc_macro2::TokenStream> {
+	let (name, generics) = match input {
+		Item::Struct(item) => (&item.ident, &item.generics),
+		Item::Enum(item) => (&item.ident, &item.generics),
+		Item::Union(item) => (&item.ident, &item.generics),
+		Item::Type(item) => (&item.ident, &item.generics),
+		_ => {
+			return Err(syn::Error::new_spanned(
+				input,
+				"#[hotbolt::main] is intended on a type implementing `hotbolt::base::Run`",
+			))
+		}
+	};
+	if !generics.params.is_empty() {
+		let message = "#[hotbolt::main] cannot export a generic type";
+		return Err(syn::Error::new_spanned(generics, message));
+	}
+
+	let run = quote! { <#name as hotbolt::base::Run> };
+	let converter = quote! { <#run::StateConverter as hotbolt::ffi::FfiState> };
+	let app = quote! { <#run::App as hotbolt::ffi::FfiApp> };
+	let app_version = quote! { <#run::AppVersion as hotbolt::ffi::FfiAppVersion> };
+	let entry_name = entry_name(options)?;
+	let entry = |symbol: &str| format_ident!("{}", entry_symbol(symbol, entry_name.as_deref()));
+	let keep = Erase::of(options)?.keep();
+	let (server_version, run_entry, app_new, app_drop) = (
+		entry(ENTRY_SERVER_VERSION),
+		entry(ENTRY_APP_RUN),
+		entry(ENTRY_APP_NEW),
+		entry(ENTRY_APP_DROP),
+	);
+	let (state_new, state_drop, state_serialize_new, state_serialize_drop) = (
+		entry(ENTRY_STATE_NEW),
+		entry(ENTRY_STATE_DROP),
+		entry(ENTRY_STATE_SERIALIZE_NEW),
+		entry(ENTRY_STATE_SERIALIZE_DROP),
+	);
+	let (version, compatible) = (entry(ENTRY_APP_VERSION), entry(ENTRY_APP_COMPATIBLE));
+	let manifest = entry(ENTRY_MANIFEST);
+	let binds = bind_exports(&entry);
 
-			#[hotbolt::hotbolt_entry_app_version]
-			fn hotbolt_auto_app_version() -> hotbolt::ffi::FfiArray<'static, u8> {
-				HotboltAutoAppVersion::app_version()
+	// Asserted at the type and options, so that a missing implementation is reported there.
+	let mut assertions = vec![quote_spanned! {name.span()=>
+		fn assert_run<T: hotbolt::base::Run>() {}
+		assert_run::<#name>();
+	}];
+	let mut optional = Vec::new();
+	let (mut layout, mut json) = (false, false);
+	for option in options {
+		let converter = quote! { #run::StateConverter };
+		let option = match option {
+			NestedMeta::Meta(Meta::NameValue(value))
+				if value.path.is_ident("name") || value.path.is_ident("erase_if") =>
+			{
+				continue
 			}
+			NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => path,
+			_ => return Err(unknown_main_option(option)),
+		};
+		optional.push(match option.get_ident().unwrap().to_string().as_str() {
+			"layout" => {
+				layout = true;
+				assertions.push(quote_spanned! {option.span()=>
+					fn assert_layout<T: hotbolt::ffi::FfiStateLayout>() {}
+					assert_layout::<#converter>();
+				});
+				let state_layout = entry(ENTRY_STATE_LAYOUT);
+				quote! {
+					#[unsafe(no_mangle)]
+					pub extern "C" fn #state_layout() -> u64 {
+						<#run::StateConverter as hotbolt::ffi::FfiStateLayout>::state_layout()
+					}
+				}
+			}
+			"json" => {
+				json = true;
+				assertions.push(quote_spanned! {option.span()=>
+					fn assert_json<T: hotbolt::converter::FfiStateJson>() {}
+					assert_json::<#converter>();
+				});
+				let (json_new, json_parse) =
+					(entry(ENTRY_STATE_JSON_NEW), entry(ENTRY_STATE_JSON_PARSE));
+				let json = quote! { <#run::StateConverter as hotbolt::converter::FfiStateJson> };
+				quote! {
+					#[unsafe(no_mangle)]
+					pub unsafe extern "C" fn #json_new(
+						state_ptr: *const std::ffi::c_void,
+					) -> hotbolt::FfiArrayMut<'static, u8> {
+						unsafe { #json::state_json_new(state_ptr) }
+					}
 
-			#[hotbolt::hotbolt_entry_app_compatible]
-			fn hotbolt_auto_app_compatible(other: hotbolt::ffi::FfiArray<'static, u8>) -> bool {
-				HotboltAutoAppVersion::app_compatible(other)
+					#[unsafe(no_mangle)]
+					pub unsafe extern "C" fn #json_parse(
+						json: hotbolt::FfiArray<'static, u8>,
+						out: *mut std::ffi::c_void,
+						visit: hotbolt::ffi::FfiStringVisitor,
+					) -> hotbolt::FfiArrayMut<'static, u8> {
+						unsafe { #json::state_json_parse(json, out, visit) }
+					}
+				}
 			}
+			_ => return Err(unknown_main_option(option)),
+		});
+	}
+
+	// The exports live in an anonymous constant, so that they cannot clash with other items.
+	Ok(quote! {
+		#keep
+		const _: () = {
+			const _: fn() = || {
+				#(#assertions)*
+			};
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #server_version() -> u8 {
+				hotbolt::ffi::SERVER_VERSION
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #run_entry(
+				app_ptr: *mut std::ffi::c_void,
+				server: hotbolt::ffi::FfiServer,
+				state_ptr: *mut std::ffi::c_void,
+			) {
+				<#name as hotbolt::ffi::FfiRun>::run(app_ptr, server, state_ptr)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #state_new(
+				serialized: hotbolt::FfiArray<'static, u8>,
+				restore: *mut hotbolt::ffi::StateRestore,
+				out: *mut std::ffi::c_void,
+				visit: hotbolt::ffi::FfiStringVisitor,
+			) -> *mut std::ffi::c_void {
+				#converter::state_new(serialized, restore, out, visit)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #state_drop(state_ptr: *mut std::ffi::c_void) {
+				#converter::state_drop(state_ptr)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #state_serialize_new(
+				state_ptr: *const std::ffi::c_void,
+			) -> hotbolt::FfiArrayMut<'static, u8> {
+				#converter::state_serialized_new(state_ptr)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #state_serialize_drop(serialized: hotbolt::FfiArrayMut<'static, u8>) {
+				#converter::state_serialized_drop(serialized)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #app_new() -> *mut std::ffi::c_void {
+				#app::app_new()
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #app_drop(app_ptr: *mut std::ffi::c_void) {
+				#app::app_drop(app_ptr)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #version() -> hotbolt::FfiArray<'static, u8> {
+				#app_version::app_version()
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #compatible(other: hotbolt::FfiArray<'static, u8>) -> bool {
+				#app_version::app_compatible(other)
+			}
+
+			#[unsafe(no_mangle)]
+			pub extern "C" fn #manifest() -> hotbolt::ffi::FfiManifest {
+				hotbolt::ffi::FfiManifest::of::<#name>(#layout, #json)
+			}
+
+			#binds
+
+			#(#optional)*
 		};
+	})
+}
+
+/// Exports the entry points binding the library to the runner, namespaced by entry so that a
+/// library with several entries does not export them twice.
+fn bind_exports(entry: &dyn Fn(&str) -> Ident) -> proc_macro2::TokenStream {
+	let (reloadable_bind, persistent_bind) =
+		(entry(ENTRY_RELOADABLE_BIND), entry(ENTRY_PERSISTENT_BIND));
+	quote! {
+		#[unsafe(no_mangle)]
+		pub unsafe extern "C" fn #reloadable_bind(table: *const hotbolt::ffi::FfiReloadTable) {
+			unsafe { hotbolt::reload::bind(table) }
+		}
 
-		TokenStream::from(expanded)
-	} else {
-		panic!("#[{}] is intended on a struct", function_name!());
+		#[unsafe(no_mangle)]
+		pub unsafe extern "C" fn #persistent_bind(store: *const hotbolt::ffi::FfiPersistentStore) {
+			unsafe { hotbolt::persistent::bind(store) }
+		}
 	}
 }
 
+fn unknown_main_option<T: quote::ToTokens>(option: T) -> syn::Error {
+	let message = concat!(
+		"unknown option, expected `layout`, `json`, `name = \"...\"` ",
+		"or `erase_if = \"...\"`",
+	);
+	syn::Error::new_spanned(option, message)
+}
+
+/// Routes calls to the function through the runner's table of reloadable functions.
+///
+/// When soft reloading, the function is replaced in place by its new build, including for code of
+/// previous builds that is still running. The function is exported under its name and the
+/// fingerprint of its signature, so names must be unique within the library and a function whose
+/// signature changed is only called by the new build.
 #[proc_macro_attribute]
-pub fn hotbolt_entry_app_version(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
-		let name = format_ident!("{}", ENTRY_APP_VERSION);
+pub fn reloadable(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let options = syn::parse_macro_input!(attr with Options::parse_terminated);
+	let input: Item = syn::parse_macro_input!(token_stream);
+	let result = match &input {
+		Item::Fn(item) => check_options(&options, &["erase_if"])
+			.and_then(|()| Erase::of(&options))
+			.and_then(|erase| hotbolt_reloadable(&erase, item)),
+		_ => Err(intended_on(function_name!(), &input, "a function")),
+	};
+	match result {
+		Ok(expanded) => TokenStream::from(expanded),
+		Err(e) => TokenStream::from(e.to_compile_error()),
+	}
+}
+
+fn hotbolt_reloadable(erase: &Erase, input: &ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+	let sig = &input.sig;
+	if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+		let message = "reloadable functions cannot be generic";
+		return Err(syn::Error::new_spanned(&sig.generics, message));
+	}
+	if let Some(token) = &sig.constness {
+		return Err(syn::Error::new_spanned(token, "reloadable functions cannot be const"));
+	}
+	if let Some(token) = &sig.asyncness {
+		return Err(syn::Error::new_spanned(token, "reloadable functions cannot be async"));
+	}
+	if let Some(variadic) = &sig.variadic {
+		return Err(syn::Error::new_spanned(variadic, "reloadable functions cannot be variadic"));
+	}
+
+	// The wrapper names every argument, since they may be patterns.
+	let mut wrapper_sig = sig.clone();
+	let mut types = Vec::new();
+	let mut args = Vec::new();
+	for (index, input) in wrapper_sig.inputs.iter_mut().enumerate() {
+		let input = match input {
+			FnArg::Typed(input) => input,
+			FnArg::Receiver(_) => {
+				let message = "reloadable functions cannot take `self`";
+				return Err(syn::Error::new_spanned(input, message));
+			}
+		};
+		let arg = format_ident!("arg{}", index);
+		*input.pat = syn::parse_quote!(#arg);
+		types.push(input.ty.clone());
+		args.push(arg);
+	}
+
+	let unsafety = &sig.unsafety;
+	let abi = &sig.abi;
+	let output = &sig.output;
+	let function_type = quote! { #unsafety #abi fn(#(#types),*) #output };
+	let fingerprint = Fingerprint::new(&function_type.to_string()).finish();
+	let symbol = format!("{}{}_{:016x}", ENTRY_RELOADABLE_PREFIX, sig.ident, fingerprint);
+	let export = format_ident!("{}", symbol);
+	let mut export_sig = sig.clone();
+	export_sig.ident = export.clone();
+
+	let attrs = &input.attrs;
+	let vis = &input.vis;
+	let block = &input.block;
+	let call = match unsafety {
+		Some(_) => quote! { unsafe { function(#(#args),*) } },
+		None => quote! { function(#(#args),*) },
+	};
+	let (erased, keep) = (erase.erased(), erase.keep());
+	Ok(quote! {
+		#erased
+		#input
+
+		#keep
+		#[unsafe(no_mangle)]
+		#export_sig #block
+
+		#keep
+		#(#attrs)*
+		#vis #wrapper_sig {
+			static SLOT: hotbolt::reload::Slot = hotbolt::reload::Slot::new();
+			let function: #function_type = #export;
+			let function = SLOT.get(#symbol, function as *const std::ffi::c_void);
+			// Every build exported under this symbol has the same signature.
+			let function = unsafe {
+				std::mem::transmute::<*const std::ffi::c_void, #function_type>(function)
+			};
+			#call
+		}
+	})
+}
+
+/// Calls the function with a [`LifecycleReason`](hotbolt_ffi::base::LifecycleReason) each time
+/// the library is loaded, before any entry point runs.
+///
+/// Useful to register callbacks again or rebuild caches after a reload. The hook is exported
+/// once per library, so it is called for every entry.
+#[proc_macro_attribute]
+pub fn on_load(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn(hotbolt::base::LifecycleReason) };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_ON_LOAD);
 		quote! {
-			fn #name() -> hotbolt::ffi::FfiArray<'static, u8> {
-				#ident()
+			fn #name(reason: hotbolt::base::LifecycleReason) {
+				#ident(reason)
 			}
 		}
 	})
 }
 
+/// Calls the function with a [`LifecycleReason`](hotbolt_ffi::base::LifecycleReason) before the
+/// library is unloaded, such as to flush logs.
+///
+/// When the process is closed, the hook runs while the entry point may still be running on another
+/// thread. It is not called when the process is killed, such as when the library does not return
+/// from the hook in time.
 #[proc_macro_attribute]
-pub fn hotbolt_entry_app_compatible(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
-		let name = format_ident!("{}", ENTRY_APP_COMPATIBLE);
+pub fn on_unload(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let signature = quote! { fn(hotbolt::base::LifecycleReason) };
+	wrap_method2(function_name!(), attr, token_stream, signature, |ident| {
+		let name = format_ident!("{}", ENTRY_ON_UNLOAD);
 		quote! {
-			fn #name(other: hotbolt::ffi::FfiArray<u8>) -> bool {
-				#ident(other)
+			fn #name(reason: hotbolt::base::LifecycleReason) {
+				#ident(reason)
 			}
 		}
 	})
@@ -281,92 +731,401 @@ pub fn hotbolt_entry_app_compatible(_attr: TokenStream, token_stream: TokenStrea
 // TODO: What to do if the main entry is named main (not allowed to our input)?
 // TODO: Clippy warning: recursing into entrypoint `main`
 /// Deprecated.
+///
+/// An `async` main entry point is run on the tokio runtime of `hotbolt::runtime::Runtime`, which
+/// is kept across soft reloads. It requires the `tokio` feature and cannot take the state.
+///
+/// `name = "editor"` exports a named entry, see [`main`](macro@main). `erase_if = "..."` sets the
+/// `cfg` predicate under which the exports are erased, which defaults to
+/// `feature = "hotbolt_erase"`.
 #[proc_macro_attribute]
-pub fn hotbolt_entry_main(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+pub fn hotbolt_entry_main(attr: TokenStream, token_stream: TokenStream) -> TokenStream {
+	let options = syn::parse_macro_input!(attr with Options::parse_terminated);
 	let input: Item = syn::parse_macro_input!(token_stream);
-	if let Item::Fn(input_function) = &input {
-		let input_function_name = &input_function.sig.ident;
-		let entry_name = format_ident!("{}", ENTRY_APP_RUN);
-
-		let input_count = input_function.sig.inputs.len();
-		let args: Vec<(Ident, syn::Path, _)> = vec![
-			(
-				format_ident!("server"),
-				syn::parse_str("hotbolt::internal::FfiServer").unwrap(),
-				quote! { server },
-			),
-			(
-				format_ident!("state"),
-				syn::parse_str("hotbolt::internal::SizedCharArray").unwrap(),
-				quote! { state.as_u8_slice() },
-			),
-		];
-
-		let export_args: Vec<_> = args
-			.iter()
-			.enumerate()
-			.map(|(index, (name, field, _))| {
-				let ident = if index < input_count {
-					name.clone()
-				} else {
-					format_ident!("_{}", name)
-				};
-				quote! { #ident: #field }
-			})
-			.collect();
+	let input_function = match &input {
+		Item::Fn(item) => item,
+		_ => {
+			let error = intended_on(function_name!(), &input, "a function");
+			return TokenStream::from(error.to_compile_error());
+		}
+	};
+	let expanded = check_options(&options, &["name", "erase_if"]).and_then(|()| {
+		if input_function.sig.asyncness.is_some() {
+			hotbolt_main_async(&options, input_function)
+		} else {
+			hotbolt_main_legacy(&options, input_function)
+		}
+	});
+	match expanded {
+		Ok(expanded) => TokenStream::from(quote! {
+			#input
 
-		let method_args: Vec<_> = args
-			.iter()
-			.take(input_count)
-			.map(|(_, _, value)| value)
-			.collect();
+			#expanded
+		}),
+		Err(e) => TokenStream::from(e.to_compile_error()),
+	}
+}
 
-		let state_entry = if input_count < 2 {
-			quote! {
-				#[hotbolt::hotbolt_entry_state_get]
-				fn hotbolt_expanded_entry() -> Vec<u8> {
-					Vec::new()
-				}
+/// Argument of the legacy main entry point, told apart by its type.
+#[derive(PartialEq)]
+enum MainArgument {
+	/// `impl Server`
+	Server,
+	/// `&[u8]`
+	State,
+	/// `&mut State`, restored using the `StateConverter` of the state.
+	StateMut,
+}
+
+impl MainArgument {
+	fn of(arg: &FnArg) -> syn::Result<Self> {
+		let ty = match arg {
+			FnArg::Typed(arg) => &*arg.ty,
+			FnArg::Receiver(_) => {
+				return Err(syn::Error::new_spanned(arg, "the main entry point cannot take `self`"))
 			}
+		};
+		match ty {
+			Type::ImplTrait(ty) if ty.bounds.iter().any(is_server_bound) => Ok(Self::Server),
+			Type::Reference(ty) if ty.mutability.is_none() && is_byte_slice(&ty.elem) => {
+				Ok(Self::State)
+			}
+			Type::Reference(ty) if ty.mutability.is_some() => Ok(Self::StateMut),
+			_ => Err(syn::Error::new_spanned(
+				ty,
+				"expected `impl Server`, `&[u8]` or `&mut State`",
+			)),
+		}
+	}
+
+	fn is_state(&self) -> bool {
+		matches!(self, Self::State | Self::StateMut)
+	}
+}
+
+fn is_server_bound(bound: &TypeParamBound) -> bool {
+	match bound {
+		TypeParamBound::Trait(bound) => {
+			bound.path.segments.last().is_some_and(|segment| segment.ident == "Server")
+		}
+		_ => false,
+	}
+}
+
+fn is_byte_slice(ty: &Type) -> bool {
+	match ty {
+		Type::Slice(slice) => matches!(&*slice.elem, Type::Path(path) if path.path.is_ident("u8")),
+		_ => false,
+	}
+}
+
+fn hotbolt_main_legacy(
+	options: &Options,
+	input_function: &ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+	let input_function_name = &input_function.sig.ident;
+	let name = entry_name(options)?;
+	let entry = |symbol: &str| format_ident!("{}", entry_symbol(symbol, name.as_deref()));
+	let entry_name = entry(ENTRY_APP_RUN);
+
+	let mut arguments = Vec::new();
+	let mut method_args = Vec::new();
+	for arg in &input_function.sig.inputs {
+		let argument = MainArgument::of(arg)?;
+		if arguments.contains(&argument) {
+			return Err(syn::Error::new_spanned(arg, "duplicate argument of the same type"));
+		}
+		if argument.is_state() && arguments.iter().any(MainArgument::is_state) {
+			return Err(syn::Error::new_spanned(arg, "the state can only be taken once"));
+		}
+		// Spanned at the argument, so that a mismatched type is reported there.
+		method_args.push(match argument {
+			MainArgument::Server => quote_spanned! {arg.span()=> server },
+			MainArgument::State => quote_spanned! {arg.span()=> state.as_u8_slice() },
+			// The state is dropped when the entry point returns, before its serialized state.
+			MainArgument::StateMut => quote_spanned! {arg.span()=>
+				&mut *unsafe { hotbolt::ffi::legacy_state(state.as_u8_slice()) }
+			},
+		});
+		arguments.push(argument);
+	}
+
+	let export_arg = |used: bool, name: &str| {
+		if used {
+			format_ident!("{}", name)
 		} else {
-			quote! {}
+			format_ident!("_{}", name)
+		}
+	};
+	let has_state = arguments.iter().any(MainArgument::is_state);
+	let server = export_arg(arguments.contains(&MainArgument::Server), "server");
+	let state = export_arg(has_state, "state");
+
+	let manifest_name = entry(ENTRY_MANIFEST);
+	let binds = bind_exports(&entry);
+	let keep = Erase::of(options)?.keep();
+
+	Ok(quote! {
+		#keep
+		#[unsafe(no_mangle)]
+		pub extern "C" fn #entry_name(
+			#server: hotbolt::internal::FfiServer,
+			#state: hotbolt::internal::SizedCharArray,
+		) {
+			#input_function_name(#(#method_args),*);
+		}
+
+		#keep
+		#[unsafe(no_mangle)]
+		pub extern "C" fn #manifest_name() -> hotbolt::ffi::FfiManifest {
+			hotbolt::ffi::FfiManifest::legacy(#has_state)
+		}
+
+		#keep
+		const _: () = {
+			#binds
 		};
+	})
+}
 
-		let expanded = quote! {
-			#input
+/// Exports an async main entry point as a [`Run`](hotbolt_ffi::base::Run) type owning a runtime.
+fn hotbolt_main_async(
+	options: &Options,
+	input_function: &ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+	let input_function_name = &input_function.sig.ident;
+	let mut method_args = Vec::new();
+	for arg in &input_function.sig.inputs {
+		if MainArgument::of(arg)?.is_state() {
+			let message = "async main entry points cannot take the state";
+			return Err(syn::Error::new_spanned(arg, message));
+		}
+		if !method_args.is_empty() {
+			return Err(syn::Error::new_spanned(arg, "duplicate argument of the same type"));
+		}
+		method_args.push(quote_spanned! {arg.span()=>
+			hotbolt::runtime::AsyncServer::new(&server)
+		});
+	}
+
+	let name = format_ident!("AsyncMain");
+	let exports = hotbolt_main(options, &syn::parse_quote!(struct #name;))?;
+	let keep = Erase::of(options)?.keep();
+	Ok(quote! {
+		#keep
+		const _: () = {
+			struct #name;
 
-			#[cfg(not(feature = "hotbolt_erase"))]
-			#[no_mangle]
-			pub extern "C" fn #entry_name(#(#export_args),*) {
-				#input_function_name(#(#method_args),*);
+			impl hotbolt::base::Run for #name {
+				type StateConverter = ();
+				type App = hotbolt::runtime::Runtime;
+				type AppVersion = hotbolt::runtime::Runtime;
+
+				fn run(
+					runtime: &mut hotbolt::runtime::Runtime,
+					server: impl hotbolt::base::Server<()>,
+					_state: &mut (),
+				) {
+					runtime.run(&server, #input_function_name(#(#method_args),*));
+				}
 			}
-			#state_entry
+
+			#exports
 		};
+	})
+}
+
+/// Implements `State` and `StateConverter` for a struct using a field-keyed encoding.
+///
+/// New fields are restored as [`State::new`](hotbolt_ffi::base::State::new), removed fields are
+/// dropped and renamed fields are found using `#[hotbolt(alias = "old_name")]`. Restored state can
+/// be checked with `#[hotbolt(validate = "function")]` on the struct.
+#[proc_macro_derive(HotboltState, attributes(hotbolt))]
+pub fn derive_hotbolt_state(token_stream: TokenStream) -> TokenStream {
+	let input: DeriveInput = syn::parse_macro_input!(token_stream);
+	hotbolt_state(&input)
+		.unwrap_or_else(|e| e.to_compile_error())
+		.into()
+}
+
+fn hotbolt_state(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+	let fields = match &input.data {
+		Data::Struct(data) => match &data.fields {
+			Fields::Named(fields) => &fields.named,
+			_ => {
+				return Err(syn::Error::new_spanned(
+					&data.fields,
+					"#[derive(HotboltState)] requires named fields",
+				))
+			}
+		},
+		_ => {
+			return Err(syn::Error::new_spanned(
+				input,
+				"#[derive(HotboltState)] is intended on a struct",
+			))
+		}
+	};
+
+	let mut idents = Vec::new();
+	let mut keys = Vec::new();
+	let mut aliases = Vec::new();
+	for field in fields {
+		let ident = field.ident.as_ref().unwrap();
+		idents.push(ident);
+		keys.push(ident.to_string());
+		let field_aliases = hotbolt_attributes(&field.attrs, &["alias"])?
+			.into_iter()
+			.map(|(_, alias)| alias.value())
+			.collect::<Vec<_>>();
+		aliases.push(field_aliases);
+	}
+
+	let validate = match hotbolt_attributes(&input.attrs, &["validate"])?.pop() {
+		Some((_, path)) => {
+			let path: syn::Path = path.parse()?;
+			quote! {
+				fn validate(state: &Self) -> Result<(), String> {
+					#path(state)
+				}
+			}
+		}
+		None => quote! {},
+	};
+
+	let name = &input.ident;
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	Ok(quote! {
+		impl #impl_generics hotbolt::base::State for #name #type_generics #where_clause {
+			fn new() -> Self {
+				Self {
+					#(#idents: hotbolt::base::State::new(),)*
+				}
+			}
+		}
 
-		TokenStream::from(expanded)
-	} else {
-		panic!("#[{}] is intended on a function", function_name!(),);
+		impl #impl_generics hotbolt::fields::FieldValue for #name #type_generics #where_clause {
+			fn encode(&self, bytes: &mut Vec<u8>) {
+				#(hotbolt::fields::encode_field(bytes, #keys, &self.#idents);)*
+			}
+
+			fn decode(
+				bytes: &[u8],
+				report: &mut hotbolt::fields::FieldReport,
+			) -> Result<Self, ()> {
+				let mut fields = hotbolt::fields::FieldsDecoder::new(bytes)?;
+				let value = Self {
+					#(#idents: fields.field(#keys, &[#(#aliases),*], report),)*
+				};
+				fields.finish(report);
+				Ok(value)
+			}
+		}
+
+		impl #impl_generics hotbolt::base::StateConverter for #name #type_generics #where_clause {
+			type State = Self;
+			type Serializer = hotbolt::fields::FieldConverter<Self>;
+			type Deserializer = hotbolt::fields::FieldConverter<Self>;
+
+			#validate
+		}
+	})
+}
+
+/// Returns the values of `#[hotbolt(key = "...")]` attributes, allowing only the given keys.
+fn hotbolt_attributes(
+	attrs: &[syn::Attribute],
+	keys: &[&str],
+) -> syn::Result<Vec<(String, syn::LitStr)>> {
+	let mut values = Vec::new();
+	for attr in attrs.iter().filter(|attr| attr.path.is_ident("hotbolt")) {
+		let list = match attr.parse_meta()? {
+			Meta::List(list) => list,
+			meta => return Err(syn::Error::new_spanned(meta, "Expected #[hotbolt(...)]")),
+		};
+		for nested in list.nested {
+			let value = match nested {
+				NestedMeta::Meta(Meta::NameValue(value)) => value,
+				nested => return Err(syn::Error::new_spanned(nested, "Expected `key = \"...\"`")),
+			};
+			let key = keys.iter().find(|key| value.path.is_ident(key)).ok_or_else(|| {
+				let expected = keys.join("`, `");
+				syn::Error::new_spanned(&value.path, format!("Expected one of `{}`", expected))
+			})?;
+			match value.lit {
+				Lit::Str(lit) => values.push((key.to_string(), lit)),
+				lit => return Err(syn::Error::new_spanned(lit, "Expected a string")),
+			}
+		}
 	}
+	Ok(values)
 }
 
-/// Deprecated.
-#[proc_macro_attribute]
-pub fn hotbolt_entry_state_get(_attr: TokenStream, token_stream: TokenStream) -> TokenStream {
-	wrap_method2(function_name!(), token_stream, |ident| {
-		let name = format_ident!("{}", ENTRY_STATE_NEW);
+/// Implements `StateLayout` for a struct, fingerprinting its size, alignment and fields.
+///
+/// Every field type must implement `StateLayout` too, so the struct is plain data as well.
+#[proc_macro_derive(StateLayout)]
+pub fn derive_state_layout(token_stream: TokenStream) -> TokenStream {
+	let input: DeriveInput = syn::parse_macro_input!(token_stream);
+	state_layout(&input)
+		.unwrap_or_else(|e| e.to_compile_error())
+		.into()
+}
+
+fn state_layout(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+	let fields = match &input.data {
+		Data::Struct(data) => &data.fields,
+		_ => {
+			return Err(syn::Error::new_spanned(
+				input,
+				"#[derive(StateLayout)] is intended on a struct",
+			))
+		}
+	};
+
+	let layout_fields = fields.iter().enumerate().map(|(index, field)| {
+		let member = match &field.ident {
+			Some(ident) => Member::Named(ident.clone()),
+			None => Member::Unnamed(index.into()),
+		};
+		let key = match &member {
+			Member::Named(ident) => ident.to_string(),
+			Member::Unnamed(index) => index.index.to_string(),
+		};
+		let ty = &field.ty;
+		let type_name = quote!(#ty).to_string();
 		quote! {
-			fn #name() -> hotbolt::internal::SizedCharArray {
-				hotbolt::internal::SizedCharArray::from_slice(&#ident())
-			}
+			.field(
+				#key,
+				#type_name,
+				::core::mem::offset_of!(Self, #member),
+				<#ty as hotbolt::layout::StateLayout>::LAYOUT,
+			)
+		}
+	});
+
+	let name = &input.ident;
+	let name_str = name.to_string();
+	let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+	Ok(quote! {
+		unsafe impl #impl_generics hotbolt::layout::StateLayout
+			for #name #type_generics #where_clause
+		{
+			const LAYOUT: u64 = hotbolt::layout::Fingerprint::of::<Self>(#name_str)
+				#(#layout_fields)*
+				.finish();
 		}
 	})
 }
 
-fn hotbolt_version() -> proc_macro2::TokenStream {
+fn hotbolt_version(erase: &Erase) -> proc_macro2::TokenStream {
 	let ident = format_ident!("{}", ENTRY_SERVER_VERSION);
+	let keep = erase.keep();
 	quote! {
-		fn #ident() -> u8 {
-			hotbolt::SERVER_VERSION
+		#keep
+		#[unsafe(no_mangle)]
+		pub extern "C" fn #ident() -> u8 {
+			hotbolt::ffi::SERVER_VERSION
 		}
 	}
 }
@@ -1,4 +1,6 @@
 use std::{
+	collections::VecDeque,
+	ffi::c_void,
 	io,
 	mem,
 	net::TcpStream,
@@ -6,7 +8,7 @@ use std::{
 	process,
 	sync::{
 		atomic::{AtomicBool, Ordering},
-		mpsc,
+		mpsc::{self, Receiver},
 		Arc,
 		Mutex,
 		RwLock,
@@ -14,21 +16,222 @@ use std::{
 	thread,
 };
 
-use log::{debug, error, info};
+use hotbolt_ffi::{
+	base::LifecycleReason,
+	ffi::{StateRestore, StateRestoreKind, SERVER_VERSION},
+	BuildStatus,
+	Server,
+};
+use log::{debug, error, info, warn};
 
 use super::runner;
 use crate::{
-	common::{ClientMessage, ServerMessage},
+	common::{decompress, ClientMessage, Compression, ServerMessage},
 	util::tcp,
 };
-use runner::{HotboltLib, SenderServer};
+use runner::{
+	HotboltLib,
+	HotboltLibRun,
+	Lifecycle,
+	PersistentStore,
+	ReloadTable,
+	SenderServer,
+	StateRequest,
+};
 use tcp::TcpPeer;
 
-pub fn start<P: AsRef<Path>>(lib_path: P, address: &str) {
+const JSON_UNSUPPORTED: &str = "JSON requires a library implementing Run";
+
+/// Compresses and tags the state sent to the server, logging the size before and after.
+fn compress(compression: Compression, state: Box<[u8]>) -> Box<[u8]> {
+	match compression.compress(&state) {
+		Ok(compressed) => {
+			if compression != Compression::None {
+				debug!(
+					"Compressed state with {} from {} to {} bytes",
+					compression,
+					state.len(),
+					compressed.len()
+				);
+			}
+			compressed
+		}
+		Err(e) => {
+			error!("{}. Sending it uncompressed", e);
+			Compression::None.compress(&state).unwrap_or(state)
+		}
+	}
+}
+
+/// Logs how the state was restored.
+///
+/// Rejected state is reported to the server instead, which restarts the client with other state.
+fn check_restore(server: &SenderServer, restore: StateRestore, notes: Vec<String>) {
+	if restore.kind == StateRestoreKind::Rejected {
+		let reason = notes.join(". ");
+		error!("{}: {}", restore, reason);
+		server.send(runner::SenderEvent::Rejected(reason), "Rejected");
+		loop {
+			thread::park();
+		}
+	}
+
+	if restore.is_reset() {
+		warn!("{}", restore);
+	} else {
+		info!("{}", restore);
+	}
+	for note in notes {
+		info!("{}", note);
+	}
+}
+
+/// Hands the state to the newly loaded library, returning the new state.
+///
+/// The state is reused as-is when both libraries export the same state layout, and serialized
+/// otherwise. Previous libraries stay loaded, since the app was created by the first one.
+///
+/// Serialized state is kept in `serialized` while the new state lives, since it may borrow it.
+/// Reloadable functions are pointed to the new library, even when called by previous libraries.
+/// Persistent statics are restored by the new library from their values in the previous one.
+/// The previous library is told it is unloaded before the new one takes over.
+fn soft_reload(
+	server: &SenderServer,
+	table: &'static ReloadTable,
+	store: &'static PersistentStore,
+	symbols: &HotboltLibRun<'static>,
+	state: *mut c_void,
+	serialized: &mut Option<Box<[u8]>>,
+	library: &'static HotboltLib,
+) -> Result<(HotboltLibRun<'static>, *mut c_void), String> {
+	if library.server_version() != Some(SERVER_VERSION) {
+		return Err("The new library does not support soft reloading".to_owned());
+	}
+	let new_symbols = library.run_symbols()?;
+	if new_symbols.has_unversioned_app() {
+		return Err("The new library has an app without an app version".to_owned());
+	}
+	if !new_symbols.app_compatible(symbols) {
+		return Err("The new library is not compatible with the app".to_owned());
+	}
+	symbols.lifecycle().on_unload(LifecycleReason::SoftReload);
+	let rebound = library.bind(table);
+	if rebound > 0 {
+		info!("Rebound {} reloadable functions", rebound);
+	}
+	store.detach();
+	library.bind_persistent(store);
+	new_symbols.lifecycle().on_load(LifecycleReason::SoftReload);
+
+	match (symbols.state_layout(), new_symbols.state_layout()) {
+		(Some(layout), Some(new_layout)) if layout == new_layout => {
+			info!("Reused state with unchanged layout {:016x}", layout);
+			Ok((new_symbols, state))
+		}
+		(layout, new_layout) => {
+			if layout.is_some() && new_layout.is_some() {
+				info!("State layout changed, serializing state");
+			}
+			let new_serialized = symbols.state(state);
+			symbols.state_drop(state);
+			let (state, restore, notes) = new_symbols.state_new(&new_serialized);
+			check_restore(server, restore, notes);
+			*serialized = Some(new_serialized);
+			Ok((new_symbols, state))
+		}
+	}
+}
+
+/// Runs a library implementing [`Run`](hotbolt_ffi::base::Run) until the process is closed.
+///
+/// The state is only accessed between runs, when requested by the server. `lifecycle` is kept
+/// pointing to the hooks of the library currently run.
+fn run_library(
+	library: &'static HotboltLib,
+	store: &'static PersistentStore,
+	lifecycle: &Mutex<Lifecycle>,
+	reason: LifecycleReason,
+	server: &SenderServer,
+	state: &[u8],
+	requests: &Receiver<StateRequest>,
+) -> Result<(), String> {
+	let table = ReloadTable::new();
+	library.bind(table);
+	let mut symbols = library.run_symbols()?;
+	*lifecycle.lock().unwrap() = symbols.lifecycle();
+	symbols.lifecycle().on_load(reason);
+	let app = symbols.app_new();
+	let (mut state, restore, notes) = symbols.state_new(state);
+	check_restore(server, restore, notes);
+	// Kept alive for the state created from it, like the state this function was called with.
+	let mut serialized = None;
+
+	loop {
+		symbols.run(app, server, state);
+		server.should_return.store(false, Ordering::Relaxed);
+		while let Ok(request) = requests.try_recv() {
+			match request {
+				StateRequest::Serialize => {
+					let serialized = symbols.has_state().then(|| symbols.state(state));
+					server.send(runner::SenderEvent::State(serialized), "State");
+				}
+				StateRequest::Json => {
+					let json = symbols.state_json(state);
+					server.send(runner::SenderEvent::Json(json), "Json");
+				}
+				StateRequest::SetJson(json) => match symbols.state_from_json(&json) {
+					Ok(state) => server.reload_with(&state),
+					Err(e) => server.send(runner::SenderEvent::Json(Err(e)), "Json"),
+				},
+				StateRequest::Restart(new_serialized) => {
+					symbols.state_drop(state);
+					let new_serialized = new_serialized.unwrap_or_default();
+					let (new_state, restore, notes) = symbols.state_new(&new_serialized);
+					check_restore(server, restore, notes);
+					info!("Soft restarted application");
+					serialized = Some(new_serialized);
+					state = new_state;
+				}
+				StateRequest::Reload(path) => {
+					let reload = HotboltLib::load(&path, library.entry()).and_then(|library| {
+						let library = Box::leak(Box::new(library));
+						soft_reload(server, table, store, &symbols, state, &mut serialized, library)
+					});
+					match reload {
+						Ok((new_symbols, new_state)) => {
+							info!("Soft reloaded library");
+							*lifecycle.lock().unwrap() = new_symbols.lifecycle();
+							symbols = new_symbols;
+							state = new_state;
+						}
+						Err(e) => {
+							warn!("{}. Restarting instead", e);
+							// Reloading without state still keeps the persistent statics.
+							if symbols.has_state() {
+								server.reload_with(&symbols.state(state));
+							} else {
+								server.reload();
+							}
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Runs the given entry of the library, or its unnamed entry.
+pub fn start<P: AsRef<Path>>(lib_path: P, address: &str, entry: Option<&str>) {
 	let (sender, receiver) = mpsc::channel();
 
+	// Interrupts reach the whole process group, but the server closes the client itself.
+	if let Err(e) = ctrlc::set_handler(|| {}) {
+		error!("Unable to handle interrupts: {}", e);
+		process::exit(1);
+	}
+
 	debug!("Connecting to server...");
-	let stream = match TcpStream::connect(&address) {
+	let stream = match TcpStream::connect(address) {
 		Ok(stream) => stream,
 		Err(e) => {
 			error!("Unable to connect to server socket on `{}`: {}", address, e);
@@ -42,13 +245,35 @@ pub fn start<P: AsRef<Path>>(lib_path: P, address: &str) {
 
 	let library: Arc<RwLock<Option<HotboltLib>>> = Arc::new(RwLock::new(None));
 	let state: Arc<Mutex<Option<Box<[u8]>>>> = Arc::new(Mutex::new(None));
+	let start_reason = Arc::new(Mutex::new(LifecycleReason::Startup));
+	let lifecycle = Arc::new(Mutex::new(Lifecycle::default()));
 	let loaded = Arc::new(AtomicBool::new(false));
+	let assets = Arc::new(Mutex::new(VecDeque::new()));
+	let build_status = Arc::new(Mutex::new(BuildStatus::Ok));
+	let run_based = Arc::new(AtomicBool::new(false));
+	let (request_sender, request_receiver) = mpsc::channel();
+	let should_return = Arc::new(AtomicBool::new(false));
+	let store = PersistentStore::new();
 
 	let library_thread = library.clone();
 	let state_thread = state.clone();
+	let start_reason_thread = start_reason.clone();
+	let lifecycle_thread = lifecycle.clone();
 	let loaded_thread = loaded.clone();
+	let assets_thread = assets.clone();
+	let build_status_thread = build_status.clone();
+	let run_based_thread = run_based.clone();
+	let should_return_thread = should_return.clone();
+	let requests = request_sender.clone();
 	thread::spawn(move || {
+		// Requests are handled once the current run returns, so the run is asked to return.
+		// Sent first, so that the flag is never cleared while a request is pending.
+		let request = |request| {
+			let _ = request_sender.send(request);
+			should_return_thread.store(true, Ordering::Relaxed);
+		};
 		let mut message_stream = TcpPeer::<ServerMessage, ClientMessage>::from(&stream);
+		let mut compression = Compression::None;
 		loop {
 			let send = |peer: &mut TcpPeer<ServerMessage, ClientMessage>, message| {
 				if let Err(e) = peer.write(message) {
@@ -56,11 +281,15 @@ pub fn start<P: AsRef<Path>>(lib_path: P, address: &str) {
 				}
 			};
 
+			// Libraries without state, or whose state cannot be read, save none.
 			let get_state = || {
 				let library_lock = library_thread.read().unwrap();
-				let library = library_lock.as_ref().unwrap();
-				let state = library.state().unwrap().state();
-				state
+				let library = library_lock.as_ref().filter(|library| library.has_state())?;
+				library.state().ok().map(|state| state.state())
+			};
+			let set_state = |compression| {
+				let state = store.save_with(get_state());
+				ClientMessage::SetState(state.map(|state| compress(compression, state)))
 			};
 
 			if let Ok(event) = receiver.try_recv() {
@@ -70,13 +299,29 @@ pub fn start<P: AsRef<Path>>(lib_path: P, address: &str) {
 						send(&mut message_stream, ClientMessage::Restart);
 					}
 					runner::SenderEvent::Reload => {
-						send(
-							&mut message_stream,
-							ClientMessage::SetState(Some(get_state())),
-						);
+						send(&mut message_stream, set_state(compression));
+						send(&mut message_stream, ClientMessage::Restart);
+					}
+					runner::SenderEvent::ReloadWith(state) => {
+						let state = store.save_with(Some(state));
+						let state = state.map(|state| compress(compression, state));
+						send(&mut message_stream, ClientMessage::SetState(state));
 						send(&mut message_stream, ClientMessage::Restart);
 					}
-					runner::SenderEvent::ReloadWith(_) => {}
+					runner::SenderEvent::State(state) => {
+						let state = store.save_with(state);
+						let state = state.map(|state| compress(compression, state));
+						send(&mut message_stream, ClientMessage::SetState(state));
+					}
+					runner::SenderEvent::Json(json) => {
+						send(&mut message_stream, ClientMessage::Json(json));
+					}
+					runner::SenderEvent::Rejected(reason) => {
+						send(&mut message_stream, ClientMessage::Rejected(reason));
+					}
+					runner::SenderEvent::Watch(path) => {
+						send(&mut message_stream, ClientMessage::Watch(path));
+					}
 				}
 			}
 
@@ -85,20 +330,65 @@ pub fn start<P: AsRef<Path>>(lib_path: P, address: &str) {
 					if let Some(event) = data {
 						match event {
 							ServerMessage::GetState => {
-								send(
-									&mut message_stream,
-									ClientMessage::SetState(Some(get_state())),
-								);
+								if run_based_thread.load(Ordering::Relaxed) {
+									// Sent by the main thread once the current run returns.
+									request(StateRequest::Serialize);
+								} else {
+									send(&mut message_stream, set_state(compression));
+								}
 							}
-							ServerMessage::Start(app_state) => {
+							ServerMessage::Start(app_state, reason) => {
+								let app_state = app_state.and_then(|state| {
+									decompress(&state)
+										.map(|decompressed| decompressed.into())
+										.map_err(|e| error!("{}. Starting without it", e))
+										.ok()
+								});
+								let app_state = store.restore_from(app_state);
 								let mut sl = state_thread.lock().expect("hi");
 								let _ = mem::replace(&mut *sl, app_state);
+								*start_reason_thread.lock().unwrap() = reason.into();
 								loaded_thread.store(true, Ordering::Relaxed);
 							}
-							ServerMessage::Close => {
-								// TODO: In the future, exit gracefully.
+							ServerMessage::Close(reason) => {
+								// The entry point may still be running, the process exits anyway.
+								lifecycle_thread.lock().unwrap().on_unload(reason.into());
 								process::exit(1);
 							}
+							ServerMessage::Compression(negotiated) => {
+								compression = negotiated;
+							}
+							ServerMessage::AssetChanged(path) => {
+								assets_thread.lock().unwrap().push_back(path);
+							}
+							ServerMessage::BuildStatus(status) => {
+								*build_status_thread.lock().unwrap() = status.into();
+							}
+							ServerMessage::GetJson => {
+								if run_based_thread.load(Ordering::Relaxed) {
+									request(StateRequest::Json);
+								} else {
+									let e = JSON_UNSUPPORTED.to_owned();
+									send(&mut message_stream, ClientMessage::Json(Err(e)));
+								}
+							}
+							ServerMessage::SetJson(json) => {
+								if run_based_thread.load(Ordering::Relaxed) {
+									request(StateRequest::SetJson(json));
+								} else {
+									let e = JSON_UNSUPPORTED.to_owned();
+									send(&mut message_stream, ClientMessage::Json(Err(e)));
+								}
+							}
+							ServerMessage::Reload(path) => {
+								if run_based_thread.load(Ordering::Relaxed) {
+									request(StateRequest::Reload(path));
+								} else {
+									// Same as `reload`.
+									send(&mut message_stream, set_state(compression));
+									send(&mut message_stream, ClientMessage::Restart);
+								}
+							}
 						}
 					}
 				}
@@ -112,31 +402,64 @@ pub fn start<P: AsRef<Path>>(lib_path: P, address: &str) {
 		}
 	});
 
-	let server = SenderServer { sender };
+	let server = SenderServer {
+		sender,
+		assets,
+		build_status,
+		should_return,
+		requests,
+	};
 	loop {
 		while !loaded.load(Ordering::Relaxed) {
 			thread::yield_now();
 		}
 
 		let load_error;
-		match HotboltLib::load(&lib_path) {
+		match HotboltLib::load(&lib_path, entry) {
 			Ok(lib) => {
-				library.write().unwrap().replace(lib);
 				info!("Successfully loaded library");
+				// The previous library, if any, is replaced by this one.
+				store.detach();
+				lib.bind_persistent(store);
+				if let Some(manifest) = lib.manifest() {
+					debug!("Library exports a {}", manifest);
+				}
 
-				let library_lock = library.as_ref().read().unwrap();
-				let library = library_lock.as_ref().unwrap();
-				match library.symbols() {
-					Ok(symbols) => {
-						let state = state.lock().unwrap();
-						let value = state.as_ref().map(|x| x.as_ref()).unwrap_or(&[]);
-						symbols.run(&server, value);
-						load_error = false;
+				let value = state.lock().unwrap().clone().unwrap_or_default();
+				let reason = *start_reason.lock().unwrap();
+				let result = match lib.server_version() {
+					Some(SERVER_VERSION) => {
+						run_based.store(true, Ordering::Relaxed);
+						// Soft reloads keep every library loaded until the process exits.
+						let lib = Box::leak(Box::new(lib));
+						run_library(
+							lib,
+							store,
+							&lifecycle,
+							reason,
+							&server,
+							&value,
+							&request_receiver,
+						)
 					}
-					Err(err) => {
-						error!("{}", err);
-						load_error = true;
+					Some(version) => Err(format!(
+						"Library requires server version {}, but the runner supports version {}",
+						version, SERVER_VERSION
+					)),
+					None => {
+						*lifecycle.lock().unwrap() = lib.lifecycle();
+						lib.lifecycle().on_load(reason);
+						library.write().unwrap().replace(lib);
+						let library_lock = library.as_ref().read().unwrap();
+						let library = library_lock.as_ref().unwrap();
+						library.symbols().map(|symbols| symbols.run(&server, &value))
 					}
+				};
+				if let Err(err) = result {
+					error!("{}", err);
+					load_error = true;
+				} else {
+					load_error = false;
 				}
 			}
 			Err(err) => {
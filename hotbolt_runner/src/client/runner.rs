@@ -1,9 +1,42 @@
-use std::{path::Path, sync::mpsc::Sender};
+use std::{
+	collections::{BTreeMap, HashMap, VecDeque},
+	ffi::c_void,
+	fmt,
+	path::{Path, PathBuf},
+	ptr::{self, NonNull},
+	sync::{
+		atomic::{AtomicBool, AtomicPtr, Ordering},
+		mpsc::Sender,
+		Arc,
+		Mutex,
+	},
+};
 
-use hotbolt_ffi::{FfiServer, Server, SizedCharArray};
+use hotbolt_ffi::{
+	ffi::{
+		self,
+		FfiBuildStatus,
+		FfiManifest,
+		FfiPersistentSave,
+		FfiPersistentStore,
+		FfiReloadTable,
+		FfiStringVisitor,
+		StateRestore,
+		StateRestoreKind,
+	},
+	base::LifecycleReason,
+	fields::{read_framed, write_framed},
+	BuildStatus,
+	FfiArray,
+	FfiArrayMut,
+	FfiServer,
+	Server,
+	SizedCharArray,
+};
 use libloading::{Library, Symbol};
+use log::{debug, warn};
 
-fn load_symbol<'a, T: 'a>(lib: &'a Library, name: &'static str) -> Result<Symbol<'a, T>, String> {
+fn load_symbol<'a, T: 'a>(lib: &'a Library, name: &str) -> Result<Symbol<'a, T>, String> {
 	unsafe {
 		let func: Result<Symbol<T>, _> = lib.get(name.as_bytes());
 		func
@@ -16,16 +49,16 @@ pub struct HotboltLibMain<'a> {
 }
 
 impl<'a> HotboltLibMain<'a> {
-	fn from(lib: &'a Library) -> Result<Self, String> {
+	fn from(lib: &'a HotboltLib) -> Result<Self, String> {
 		Ok(Self {
-			run: load_symbol(lib, hotbolt_ffi::ffi::ENTRY_APP_RUN)?,
+			run: lib.entry_symbol(hotbolt_ffi::ffi::ENTRY_APP_RUN)?,
 		})
 	}
 
 	pub fn run<T: Server>(&self, server: &T, state: &[u8]) {
 		unsafe {
 			(self.run)(
-				FfiServer::from::<T>(&server),
+				FfiServer::from::<T>(server),
 				SizedCharArray::from_slice(state),
 			)
 		}
@@ -37,9 +70,9 @@ pub struct HotboltLibState<'a> {
 }
 
 impl<'a> HotboltLibState<'a> {
-	fn from(lib: &'a Library) -> Result<Self, String> {
+	fn from(lib: &'a HotboltLib) -> Result<Self, String> {
 		Ok(Self {
-			state: load_symbol(lib, hotbolt_ffi::ffi::ENTRY_STATE_NEW)?,
+			state: lib.entry_symbol(hotbolt_ffi::ffi::ENTRY_STATE_NEW)?,
 		})
 	}
 
@@ -52,24 +85,560 @@ impl<'a> HotboltLibState<'a> {
 	}
 }
 
+type RunFn = unsafe extern "C" fn(app: *mut c_void, server: ffi::FfiServer, state: *mut c_void);
+type StateNewFn = unsafe extern "C" fn(
+	serialized: FfiArray<'_, u8>,
+	restore: *mut StateRestore,
+	out: *mut c_void,
+	visit: FfiStringVisitor,
+) -> *mut c_void;
+type StateSerializeNewFn = unsafe extern "C" fn(state: *const c_void) -> FfiArrayMut<'static, u8>;
+type StateSerializeDropFn = unsafe extern "C" fn(serialized: FfiArrayMut<'static, u8>);
+type StateDropFn = unsafe extern "C" fn(state: *mut c_void);
+type StateLayoutFn = unsafe extern "C" fn() -> u64;
+type AppNewFn = unsafe extern "C" fn() -> *mut c_void;
+type AppVersionFn = unsafe extern "C" fn() -> FfiArray<'static, u8>;
+type AppCompatibleFn = unsafe extern "C" fn(other: FfiArray<'static, u8>) -> bool;
+type StateJsonNewFn = unsafe extern "C" fn(state: *const c_void) -> FfiArrayMut<'static, u8>;
+type LifecycleFn = unsafe extern "C" fn(reason: LifecycleReason);
+type StateJsonParseFn = unsafe extern "C" fn(
+	json: FfiArray<'_, u8>,
+	out: *mut c_void,
+	visit: FfiStringVisitor,
+) -> FfiArrayMut<'static, u8>;
+
+const NO_JSON: &str = "The library state does not support JSON. Use a serde converter";
+
+unsafe extern "C" fn visit_string(out: *mut c_void, value: FfiArray<'static, u8>) {
+	let out = &mut *(out as *mut Option<String>);
+	*out = Some(String::from_utf8_lossy(value.as_slice()).into_owned());
+}
+
+unsafe extern "C" fn visit_note(out: *mut c_void, value: FfiArray<'static, u8>) {
+	let out = &mut *(out as *mut Vec<String>);
+	out.push(String::from_utf8_lossy(value.as_slice()).into_owned());
+}
+
+/// Entry points of libraries implementing [`Run`](hotbolt_ffi::base::Run).
+pub struct HotboltLibRun<'a> {
+	run: Symbol<'a, RunFn>,
+	state_new: Symbol<'a, StateNewFn>,
+	state_serialize_new: Symbol<'a, StateSerializeNewFn>,
+	state_serialize_drop: Symbol<'a, StateSerializeDropFn>,
+	state_drop: Option<Symbol<'a, StateDropFn>>,
+	state_layout: Option<Symbol<'a, StateLayoutFn>>,
+	app_new: Option<Symbol<'a, AppNewFn>>,
+	app_version: Option<Symbol<'a, AppVersionFn>>,
+	app_compatible: Option<Symbol<'a, AppCompatibleFn>>,
+	state_json_new: Option<Symbol<'a, StateJsonNewFn>>,
+	state_json_parse: Option<Symbol<'a, StateJsonParseFn>>,
+	has_state: bool,
+	has_unversioned_app: bool,
+	lifecycle: Lifecycle,
+}
+
+impl<'a> HotboltLibRun<'a> {
+	fn from(lib: &'a HotboltLib) -> Result<Self, String> {
+		let manifest = lib.manifest();
+		let app_new = lib.entry_symbol(ffi::ENTRY_APP_NEW).ok();
+		let app_version = lib.entry_symbol(ffi::ENTRY_APP_VERSION).ok();
+		let app_compatible = lib.entry_symbol(ffi::ENTRY_APP_COMPATIBLE).ok();
+		// Without a manifest, the exported symbols tell what the library has.
+		let has_unversioned_app = match manifest {
+			Some(manifest) => manifest.app && !manifest.app_version,
+			None => app_new.is_some() && (app_version.is_none() || app_compatible.is_none()),
+		};
+		Ok(Self {
+			run: lib.entry_symbol(ffi::ENTRY_APP_RUN)?,
+			state_new: lib.entry_symbol(ffi::ENTRY_STATE_NEW)?,
+			state_serialize_new: lib.entry_symbol(ffi::ENTRY_STATE_SERIALIZE_NEW)?,
+			state_serialize_drop: lib.entry_symbol(ffi::ENTRY_STATE_SERIALIZE_DROP)?,
+			state_drop: lib.entry_symbol(ffi::ENTRY_STATE_DROP).ok(),
+			state_layout: lib.entry_symbol(ffi::ENTRY_STATE_LAYOUT).ok(),
+			app_new,
+			app_version,
+			app_compatible,
+			state_json_new: lib.entry_symbol(ffi::ENTRY_STATE_JSON_NEW).ok(),
+			state_json_parse: lib.entry_symbol(ffi::ENTRY_STATE_JSON_PARSE).ok(),
+			has_state: manifest.map_or(true, |manifest| manifest.state),
+			has_unversioned_app,
+			lifecycle: lib.lifecycle(),
+		})
+	}
+
+	pub fn lifecycle(&self) -> Lifecycle {
+		self.lifecycle
+	}
+
+	/// Returns true if the library has state to save.
+	pub fn has_state(&self) -> bool {
+		self.has_state
+	}
+
+	/// Returns true if the library has an app, but cannot tell which apps it is compatible with.
+	pub fn has_unversioned_app(&self) -> bool {
+		self.has_unversioned_app
+	}
+
+	/// Creates the app, or a placeholder if the library has no app.
+	pub fn app_new(&self) -> *mut c_void {
+		match &self.app_new {
+			Some(app_new) => unsafe { app_new() },
+			None => NonNull::<c_void>::dangling().as_ptr(),
+		}
+	}
+
+	/// Returns true if this library can keep using the app created by the other library.
+	///
+	/// Libraries without an app version are only compatible with each other, since they have no app
+	/// or [`has_unversioned_app`](HotboltLibRun::has_unversioned_app).
+	pub fn app_compatible(&self, other: &HotboltLibRun<'_>) -> bool {
+		match (&self.app_compatible, &other.app_version) {
+			(Some(app_compatible), Some(app_version)) => unsafe { app_compatible(app_version()) },
+			(None, None) => true,
+			_ => false,
+		}
+	}
+
+	/// Returns the fingerprint of the state layout, if the library exports one.
+	pub fn state_layout(&self) -> Option<u64> {
+		self.state_layout
+			.as_ref()
+			.map(|state_layout| unsafe { state_layout() })
+	}
+
+	/// Drops the state, unless the library skips dropping it.
+	pub fn state_drop(&self, state: *mut c_void) {
+		if let Some(state_drop) = &self.state_drop {
+			unsafe { state_drop(state) };
+		}
+	}
+
+	/// Creates the state from the serialized state, migrating it when needed.
+	pub fn state_new(&self, serialized: &[u8]) -> (*mut c_void, StateRestore, Vec<String>) {
+		let mut restore = StateRestore {
+			kind: StateRestoreKind::New,
+			from: 0,
+			to: 0,
+		};
+		let mut notes = Vec::new();
+		let out = &mut notes as *mut Vec<String> as *mut c_void;
+		let state = unsafe { (self.state_new)(serialized.into(), &mut restore, out, visit_note) };
+		(state, restore, notes)
+	}
+
+	pub fn state(&self, state: *const c_void) -> Box<[u8]> {
+		let serialized = unsafe { (self.state_serialize_new)(state) };
+		let bytes = unsafe { serialized.as_slice() }.to_vec().into_boxed_slice();
+		unsafe { (self.state_serialize_drop)(serialized) };
+		bytes
+	}
+
+	/// Renders the state as JSON, if supported by the library.
+	pub fn state_json(&self, state: *const c_void) -> Result<String, String> {
+		let state_json_new = self.state_json_new.as_ref().ok_or(NO_JSON)?;
+		let json = unsafe { state_json_new(state) };
+		let text = String::from_utf8_lossy(unsafe { json.as_slice() }).into_owned();
+		unsafe { (self.state_serialize_drop)(json) };
+		if text.is_empty() {
+			Err("Unable to convert the state to JSON".to_owned())
+		} else {
+			Ok(text)
+		}
+	}
+
+	/// Serializes the state described by the JSON document, if supported by the library.
+	pub fn state_from_json(&self, json: &str) -> Result<Box<[u8]>, String> {
+		let state_json_parse = self.state_json_parse.as_ref().ok_or(NO_JSON)?;
+		let mut error: Option<String> = None;
+		let out = &mut error as *mut Option<String> as *mut c_void;
+		let serialized = unsafe { state_json_parse(json.into(), out, visit_string) };
+		let bytes = unsafe { serialized.as_slice() }.to_vec().into_boxed_slice();
+		unsafe { (self.state_serialize_drop)(serialized) };
+		match error {
+			Some(e) => Err(e),
+			None => Ok(bytes),
+		}
+	}
+
+	pub fn run(&self, app: *mut c_void, server: &SenderServer, state: *mut c_void) {
+		unsafe { (self.run)(app, server.ffi(), state) }
+	}
+}
+
+/// The [`ENTRY_ON_LOAD`](ffi::ENTRY_ON_LOAD) and [`ENTRY_ON_UNLOAD`](ffi::ENTRY_ON_UNLOAD) hooks
+/// of a library, if it exports them. The library must stay loaded while they are called.
+#[derive(Copy, Clone, Default)]
+pub struct Lifecycle {
+	on_load: Option<LifecycleFn>,
+	on_unload: Option<LifecycleFn>,
+}
+
+impl Lifecycle {
+	pub fn on_load(&self, reason: LifecycleReason) {
+		debug!("Loaded library: {:?}", reason);
+		if let Some(on_load) = self.on_load {
+			unsafe { on_load(reason) }
+		}
+	}
+
+	pub fn on_unload(&self, reason: LifecycleReason) {
+		debug!("Unloading library: {:?}", reason);
+		if let Some(on_unload) = self.on_unload {
+			unsafe { on_unload(reason) }
+		}
+	}
+}
+
+/// What a library exports, read from its [`ENTRY_MANIFEST`](ffi::ENTRY_MANIFEST).
+///
+/// See [`FfiManifest`](FfiManifest).
+pub struct Manifest {
+	pub server_version: u8,
+	pub run: bool,
+	pub state: bool,
+	pub app: bool,
+	pub app_version: bool,
+	pub state_layout: bool,
+	pub state_json: bool,
+	pub state_converter: String,
+	pub version: String,
+}
+
+impl Manifest {
+	fn from(manifest: FfiManifest) -> Self {
+		let string = |value: FfiArray<'static, u8>| {
+			String::from_utf8_lossy(unsafe { value.as_slice() }).into_owned()
+		};
+		Self {
+			server_version: manifest.server_version,
+			run: manifest.run,
+			state: manifest.state,
+			app: manifest.app,
+			app_version: manifest.app_version,
+			state_layout: manifest.state_layout,
+			state_json: manifest.state_json,
+			state_converter: string(manifest.state_converter),
+			version: string(manifest.version),
+		}
+	}
+}
+
+impl fmt::Display for Manifest {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.run {
+			write!(f, "Run entry point for server version {}", self.server_version)?;
+		} else {
+			write!(f, "legacy main entry point")?;
+		}
+		if self.state {
+			write!(f, ", state converted by `{}`", self.state_converter)?;
+		}
+		if self.app_version {
+			write!(f, ", app version `{}`", self.version)?;
+		} else if self.app {
+			write!(f, ", unversioned app")?;
+		}
+		if self.state_layout {
+			write!(f, ", state layout")?;
+		}
+		if self.state_json {
+			write!(f, ", JSON")?;
+		}
+		Ok(())
+	}
+}
+
 pub struct HotboltLib {
 	lib: Library,
+	entry: Option<String>,
+	manifest: Option<Manifest>,
 	// server: &'a T,
 }
 
 impl HotboltLib {
-	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-		Library::new(path.as_ref().as_os_str())
-			.map(|lib| Self { lib })
-			.map_err(|_err| "Error loading entry point".to_owned())
+	/// Loads the library and reads the manifest of the given entry, if it exports one.
+	///
+	/// Libraries export every entry point of named entries under namespaced symbols, see
+	/// [`entry_symbol`](ffi::entry_symbol).
+	pub fn load<P: AsRef<Path>>(path: P, entry: Option<&str>) -> Result<Self, String> {
+		let lib = Library::new(path.as_ref().as_os_str())
+			.map_err(|_err| "Error loading entry point".to_owned())?;
+		let mut lib = Self {
+			lib,
+			entry: entry.map(str::to_owned),
+			manifest: None,
+		};
+		type ManifestFn = unsafe extern "C" fn() -> FfiManifest;
+		lib.manifest = lib
+			.entry_symbol::<ManifestFn>(ffi::ENTRY_MANIFEST)
+			.ok()
+			.map(|manifest| Manifest::from(unsafe { manifest() }));
+		Ok(lib)
+	}
+
+	/// Returns the name of the entry run from this library, or `None` for the unnamed entry.
+	pub fn entry(&self) -> Option<&str> {
+		self.entry.as_deref()
+	}
+
+	/// Loads the symbol of an entry point exported by the entry run from this library.
+	fn entry_symbol<T>(&self, symbol: &str) -> Result<Symbol<'_, T>, String> {
+		load_symbol(&self.lib, &ffi::entry_symbol(symbol, self.entry()))
+	}
+
+	pub fn manifest(&self) -> Option<&Manifest> {
+		self.manifest.as_ref()
+	}
+
+	/// Returns true if the library has state to save. Libraries without a manifest are assumed to
+	/// have state when they export it.
+	pub fn has_state(&self) -> bool {
+		self.manifest.as_ref().map_or(true, |manifest| manifest.state)
 	}
 
 	pub fn symbols(&self) -> Result<HotboltLibMain<'_>, String> {
-		HotboltLibMain::from(&self.lib)
+		HotboltLibMain::from(self)
 	}
 
 	pub fn state(&self) -> Result<HotboltLibState<'_>, String> {
-		HotboltLibState::from(&self.lib)
+		HotboltLibState::from(self)
+	}
+
+	/// Returns the server version the library was built for, if it implements
+	/// [`Run`](hotbolt_ffi::base::Run).
+	pub fn server_version(&self) -> Option<u8> {
+		if let Some(manifest) = &self.manifest {
+			return manifest.run.then_some(manifest.server_version);
+		}
+		self.entry_symbol::<unsafe extern "C" fn() -> u8>(ffi::ENTRY_SERVER_VERSION)
+			.ok()
+			.map(|server_version| unsafe { server_version() })
+	}
+
+	pub fn run_symbols(&self) -> Result<HotboltLibRun<'_>, String> {
+		HotboltLibRun::from(self)
+	}
+
+	/// Returns the lifecycle hooks of the library, which are shared by every entry.
+	pub fn lifecycle(&self) -> Lifecycle {
+		let hook = |symbol| load_symbol::<LifecycleFn>(&self.lib, symbol).ok().map(|hook| *hook);
+		Lifecycle {
+			on_load: hook(ffi::ENTRY_ON_LOAD),
+			on_unload: hook(ffi::ENTRY_ON_UNLOAD),
+		}
+	}
+
+	/// Points the reloadable functions exported by this library to it, then binds it to the table.
+	///
+	/// Returns the number of functions pointed to this library. The library must stay loaded.
+	pub fn bind(&'static self, table: &'static ReloadTable) -> usize {
+		let mut rebound = 0;
+		for (symbol, entry) in table.entries.lock().unwrap().iter() {
+			if let Ok(function) = load_symbol::<*mut c_void>(&self.lib, symbol) {
+				entry.store(*function, Ordering::Release);
+				rebound += 1;
+			}
+		}
+		type BindFn = unsafe extern "C" fn(table: *const FfiReloadTable);
+		if let Ok(bind) = self.entry_symbol::<BindFn>(ffi::ENTRY_RELOADABLE_BIND) {
+			unsafe { bind(&table.ffi) };
+		}
+		rebound
+	}
+
+	/// Binds the library to the store of persistent statics, restoring them on first use.
+	///
+	/// The statics of the previous library must be [`detach`](PersistentStore::detach)ed first.
+	pub fn bind_persistent(&self, store: &'static PersistentStore) {
+		type BindFn = unsafe extern "C" fn(store: *const FfiPersistentStore);
+		if let Ok(bind) = self.entry_symbol::<BindFn>(ffi::ENTRY_PERSISTENT_BIND) {
+			unsafe { bind(&store.ffi) };
+		}
+	}
+}
+
+/// Table of the reloadable functions of every library loaded by the client, see
+/// [`reload`](hotbolt_ffi::reload).
+pub struct ReloadTable {
+	entries: Mutex<HashMap<String, &'static AtomicPtr<c_void>>>,
+	ffi: FfiReloadTable,
+}
+
+impl ReloadTable {
+	pub fn new() -> &'static Self {
+		unsafe extern "C" fn entry(
+			table: *const c_void,
+			symbol: FfiArray<'_, u8>,
+			function: *const c_void,
+		) -> *const AtomicPtr<c_void> {
+			let table = &*(table as *const ReloadTable);
+			let symbol = String::from_utf8_lossy(symbol.as_slice()).into_owned();
+			let mut entries = table.entries.lock().unwrap();
+			*entries
+				.entry(symbol)
+				.or_insert_with(|| Box::leak(Box::new(AtomicPtr::new(function as *mut c_void))))
+		}
+
+		// Libraries keep a pointer to the table, so it is never freed.
+		let table = Box::leak(Box::new(Self {
+			entries: Mutex::new(HashMap::new()),
+			ffi: FfiReloadTable {
+				table: ptr::null(),
+				entry,
+			},
+		}));
+		table.ffi.table = table as *const Self as *const c_void;
+		table
+	}
+}
+
+/// Prefixes state sent along with persistent statics.
+const PERSISTENT_MAGIC: &[u8] = b"\0hotbolt persistent\0";
+
+unsafe extern "C" fn visit_bytes(out: *mut c_void, value: FfiArray<'_, u8>) {
+	let out = &mut *(out as *mut Option<Box<[u8]>>);
+	*out = Some(value.as_slice().into());
+}
+
+/// Saved value of a persistent static, and the static itself while its library is current.
+#[derive(Default)]
+struct PersistentStatic {
+	value: Option<Box<[u8]>>,
+	live: Option<(*const c_void, FfiPersistentSave)>,
+}
+
+// Persistent statics are `Sync`, and only accessed through their save callback.
+unsafe impl Send for PersistentStatic {}
+
+impl PersistentStatic {
+	/// Updates the saved value from the static, unless it was not initialized yet.
+	fn save(&mut self) {
+		if let Some((value, save)) = self.live {
+			let mut saved: Option<Box<[u8]>> = None;
+			let out = &mut saved as *mut Option<Box<[u8]>> as *mut c_void;
+			unsafe { save(value, out, visit_bytes) };
+			if saved.is_some() {
+				self.value = saved;
+			}
+		}
+	}
+}
+
+/// Persistent statics of every library loaded by the client, see
+/// [`persistent`](hotbolt_ffi::persistent).
+///
+/// The statics are saved along with the state, so that they survive restarting the client.
+pub struct PersistentStore {
+	statics: Mutex<BTreeMap<String, PersistentStatic>>,
+	ffi: FfiPersistentStore,
+}
+
+// The FFI store only points to the store itself.
+unsafe impl Sync for PersistentStore {}
+
+impl PersistentStore {
+	pub fn new() -> &'static Self {
+		unsafe extern "C" fn register(
+			store: *const c_void,
+			name: FfiArray<'_, u8>,
+			value: *const c_void,
+			save: FfiPersistentSave,
+			out: *mut c_void,
+			visit: ffi::FfiBytesVisitor,
+		) {
+			let store = &*(store as *const PersistentStore);
+			let name = String::from_utf8_lossy(name.as_slice()).into_owned();
+			let mut statics = store.statics.lock().unwrap();
+			let entry = statics.entry(name).or_default();
+			entry.live = Some((value, save));
+			if let Some(saved) = &entry.value {
+				visit(out, FfiArray::from(&saved[..]));
+			}
+		}
+
+		// Libraries keep a pointer to the store, so it is never freed.
+		let store = Box::leak(Box::new(Self {
+			statics: Mutex::new(BTreeMap::new()),
+			ffi: FfiPersistentStore {
+				store: ptr::null(),
+				register,
+			},
+		}));
+		store.ffi.store = store as *const Self as *const c_void;
+		store
+	}
+
+	/// Saves the statics registered so far and stops saving them, before their library is
+	/// unloaded or soft reloaded. The next library restores them from the saved values.
+	pub fn detach(&self) {
+		for entry in self.statics.lock().unwrap().values_mut() {
+			entry.save();
+			entry.live = None;
+		}
+	}
+
+	/// Returns the state with the statics saved along with it, or the state as-is without statics.
+	pub fn save_with(&self, state: Option<Box<[u8]>>) -> Option<Box<[u8]>> {
+		let mut statics = Vec::new();
+		for (name, entry) in self.statics.lock().unwrap().iter_mut() {
+			entry.save();
+			if let Some(value) = &entry.value {
+				write_framed(&mut statics, name.as_bytes());
+				write_framed(&mut statics, value);
+			}
+		}
+		if statics.is_empty() {
+			return state;
+		}
+
+		let mut bytes = PERSISTENT_MAGIC.to_vec();
+		write_framed(&mut bytes, &statics);
+		if let Some(state) = state {
+			bytes.push(1);
+			bytes.extend_from_slice(&state);
+		} else {
+			bytes.push(0);
+		}
+		Some(bytes.into_boxed_slice())
+	}
+
+	/// Restores the statics saved by [`save_with`](PersistentStore::save_with), returning the state
+	/// saved along with them.
+	pub fn restore_from(&self, state: Option<Box<[u8]>>) -> Option<Box<[u8]>> {
+		let bytes = match &state {
+			Some(bytes) if bytes.starts_with(PERSISTENT_MAGIC) => &bytes[PERSISTENT_MAGIC.len()..],
+			_ => return state,
+		};
+		let restored = (|| {
+			let mut bytes = bytes;
+			let mut statics_bytes = read_framed(&mut bytes)?;
+			let mut statics = Vec::new();
+			while !statics_bytes.is_empty() {
+				let name = String::from_utf8_lossy(read_framed(&mut statics_bytes)?).into_owned();
+				statics.push((name, read_framed(&mut statics_bytes)?));
+			}
+			let state = match bytes.split_first().ok_or(())? {
+				(0, []) => None,
+				(1, state) => Some(state.into()),
+				_ => return Err(()),
+			};
+			Ok((statics, state))
+		})();
+
+		let (statics, state) = match restored {
+			Ok(restored) => restored,
+			Err(()) => {
+				warn!("Unable to read the persistent statics. Starting without them");
+				return None;
+			}
+		};
+		debug!("Restored {} persistent statics", statics.len());
+		let mut entries = self.statics.lock().unwrap();
+		for (name, value) in statics {
+			entries.entry(name).or_default().value = Some(value.into());
+		}
+		state
 	}
 }
 
@@ -78,19 +647,119 @@ pub enum SenderEvent {
 	Restart,
 	Reload,
 	ReloadWith(Box<[u8]>),
+	/// The state, or `None` for libraries without state.
+	State(Option<Box<[u8]>>),
+	Json(Result<String, String>),
+	Rejected(String),
+	Watch(PathBuf),
+}
+
+/// Requests for the state, handled between runs of libraries implementing
+/// [`Run`](hotbolt_ffi::base::Run).
+#[derive(Debug)]
+pub enum StateRequest {
+	Serialize,
+	Json,
+	/// Restarts with the state described by the JSON document.
+	SetJson(String),
+	/// Soft reloads the library copied to the given path.
+	Reload(PathBuf),
+	/// Restarts the application with the given state, or a new state, keeping the client running.
+	Restart(Option<Box<[u8]>>),
 }
 
 pub struct SenderServer {
 	pub sender: Sender<SenderEvent>,
+	pub assets: Arc<Mutex<VecDeque<PathBuf>>>,
+	pub build_status: Arc<Mutex<BuildStatus>>,
+	/// Set while requests wait for the current run to return.
+	pub should_return: Arc<AtomicBool>,
+	/// Requests sent by the library itself, such as to restart softly.
+	pub requests: Sender<StateRequest>,
 }
 
 impl SenderServer {
 	// TODO: Implement Display for Event?
-	fn send(&self, event: SenderEvent, display: &'static str) {
+	pub fn send(&self, event: SenderEvent, display: &'static str) {
 		self.sender.send(event).unwrap_or_else(|_err| {
 			panic!("hotbolt server `{}` message failed to send", display);
 		});
 	}
+
+	/// Restarts the application in this process once the current run returns.
+	fn restart_soft(&self, state: Option<Box<[u8]>>) {
+		// Sent first, so that the flag is never cleared while the request is pending.
+		let _ = self.requests.send(StateRequest::Restart(state));
+		self.should_return.store(true, Ordering::Relaxed);
+	}
+
+	/// Returns the server for libraries implementing [`Run`](hotbolt_ffi::base::Run).
+	fn ffi(&self) -> ffi::FfiServer {
+		unsafe fn server<'a>(ptr: *const c_void) -> &'a SenderServer {
+			&*(ptr as *const SenderServer)
+		}
+
+		unsafe extern "C" fn restart(ptr: *const c_void) {
+			server(ptr).restart();
+		}
+
+		unsafe extern "C" fn restart_with(ptr: *const c_void, state: FfiArray<'_, u8>) {
+			server(ptr).reload_with(state.as_slice());
+		}
+
+		unsafe extern "C" fn restart_soft(ptr: *const c_void) {
+			server(ptr).restart_soft(None);
+		}
+
+		unsafe extern "C" fn restart_soft_with(ptr: *const c_void, state: FfiArray<'_, u8>) {
+			server(ptr).restart_soft(Some(state.as_slice().into()));
+		}
+
+		unsafe extern "C" fn watch(ptr: *const c_void, path: FfiArray<'static, u8>) {
+			server(ptr).watch(&String::from_utf8_lossy(path.as_slice()));
+		}
+
+		unsafe extern "C" fn asset_changed(
+			ptr: *const c_void,
+			out: *mut c_void,
+			visit: FfiStringVisitor,
+		) {
+			if let Some(path) = server(ptr).asset_changed() {
+				visit(out, path.as_str().into());
+			}
+		}
+
+		unsafe extern "C" fn build_status(
+			ptr: *const c_void,
+			out: *mut c_void,
+			visit: FfiStringVisitor,
+		) -> FfiBuildStatus {
+			match server(ptr).build_status() {
+				BuildStatus::Ok => FfiBuildStatus::Ok,
+				BuildStatus::Building => FfiBuildStatus::Building,
+				BuildStatus::Failed(summary) => {
+					visit(out, summary.as_str().into());
+					FfiBuildStatus::Failed
+				}
+			}
+		}
+
+		unsafe extern "C" fn should_return(ptr: *const c_void) -> bool {
+			server(ptr).should_return.load(Ordering::Relaxed)
+		}
+
+		ffi::FfiServer {
+			server: self as *const SenderServer as *const c_void,
+			restart_hard: restart,
+			restart_hard_with: restart_with,
+			restart_soft,
+			restart_soft_with,
+			watch,
+			asset_changed,
+			build_status,
+			should_return,
+		}
+	}
 }
 
 impl Server for SenderServer {
@@ -105,4 +774,16 @@ impl Server for SenderServer {
 		let box_slice = state.to_vec().into_boxed_slice();
 		self.send(SenderEvent::ReloadWith(box_slice), "ReloadWith");
 	}
+	fn watch(&self, path: &str) {
+		self.send(SenderEvent::Watch(PathBuf::from(path)), "Watch");
+	}
+	fn asset_changed(&self) -> Option<String> {
+		let mut assets = self.assets.lock().unwrap();
+		assets
+			.pop_front()
+			.map(|path| path.to_string_lossy().into_owned())
+	}
+	fn build_status(&self) -> BuildStatus {
+		self.build_status.lock().unwrap().clone()
+	}
 }
@@ -5,7 +5,8 @@ use std::{
 
 use clap::Parser;
 
-use crate::util::cargo;
+use super::Compression;
+use crate::util::cargo::{self, CargoOptions};
 
 fn path_validator(input: &str) -> Result<(), String> {
 	let path: &Path = input.as_ref();
@@ -14,6 +15,15 @@ fn path_validator(input: &str) -> Result<(), String> {
 	}
 	Ok(())
 }
+
+fn poll_interval_validator(input: &str) -> Result<(), String> {
+	match input.parse::<u64>() {
+		Ok(0) => Err("The polling interval must be at least 1 millisecond.".to_owned()),
+		Ok(_) => Ok(()),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
 #[derive(Parser)]
 #[clap(version = "0.1")]
 pub struct Cli {
@@ -29,6 +39,22 @@ pub struct Cli {
 	#[clap(long, default_value = "debug", conflicts_with = "file")]
 	pub profile: String,
 
+	/// The target triple to use (when not using --file)
+	#[clap(long, conflicts_with = "file")]
+	pub target: Option<String>,
+
+	/// The package to use in a workspace (when not using --file)
+	#[clap(short, long, conflicts_with = "file")]
+	pub package: Option<String>,
+
+	/// The cdylib example to use instead of the library (when not using --file)
+	#[clap(long, conflicts_with = "file")]
+	pub example: Option<String>,
+
+	/// Builds the Cargo project each time its sources change (when not using --file)
+	#[clap(long, conflicts_with = "file")]
+	pub build: bool,
+
 	/// The hostname for the server/client connection.
 	#[clap(long, default_value = "localhost")]
 	pub host: String,
@@ -37,11 +63,49 @@ pub struct Cli {
 	#[clap(long, default_value = "49152")]
 	pub port: String,
 
-	// TODO: Need a way to pass polling duration.
 	/// The server watcher type.
 	#[clap(long, default_value = "poll")]
 	pub watcher: WatcherType,
 
+	/// The polling interval in milliseconds (when using the poll watcher).
+	#[clap(long, default_value = "2000", validator = poll_interval_validator)]
+	pub poll_interval: u64,
+
+	/// Only restarts when the library content changes, ignoring rebuilds that produce the same file.
+	#[clap(long)]
+	pub content_hash: bool,
+
+	/// Saves the state to the given file and restores it when the runner starts again.
+	#[clap(long, parse(from_os_str))]
+	pub state_file: Option<PathBuf>,
+
+	/// The number of received states kept as snapshots to rewind to.
+	#[clap(long, default_value = "10")]
+	pub snapshots: usize,
+
+	/// The local port of the control socket accepting commands, such as rewinding to a snapshot.
+	#[clap(long)]
+	pub control_port: Option<String>,
+
+	/// Compresses the state sent between the server and client, and saved to the state file: none,
+	/// lz4 or zstd.
+	#[clap(long, default_value = "none")]
+	pub compression: Compression,
+
+	/// What to restart with when the library rejects the state: fresh (new state) or rollback (the
+	/// previous snapshot).
+	#[clap(long, default_value = "fresh")]
+	pub rejected_state: RejectedState,
+
+	/// Reloads the library without restarting the client process, keeping the app and reusing the
+	/// state when its layout is unchanged.
+	#[clap(long)]
+	pub soft: bool,
+
+	/// The named entry to run, for libraries exporting several main entry points.
+	#[clap(long)]
+	pub entry: Option<String>,
+
 	/// Whether the application is started in client mode or server mode.
 	#[clap(long)]
 	pub client: bool,
@@ -64,16 +128,42 @@ impl FromStr for WatcherType {
 	}
 }
 
+pub enum RejectedState {
+	Fresh,
+	Rollback,
+}
+
+impl FromStr for RejectedState {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"fresh" => Ok(RejectedState::Fresh),
+			"rollback" => Ok(RejectedState::Rollback),
+			_ => Err("no match"),
+		}
+	}
+}
+
 impl Cli {
 	pub fn parse() -> Self {
 		Parser::parse()
 	}
 
+	pub fn cargo_options(&self) -> CargoOptions {
+		CargoOptions {
+			profile: self.profile.clone(),
+			target: self.target.clone(),
+			package: self.package.clone(),
+			example: self.example.clone(),
+		}
+	}
+
 	pub fn library_path(&self) -> Result<PathBuf, String> {
 		let path: &Path = self.input.as_ref();
 		if !self.file {
 			if path.is_dir() {
-				cargo::cargo_target_lib_path(path, &self.profile)
+				cargo::cargo_target_lib_path(path, &self.cargo_options())
 			} else {
 				Err(format!(
 					"Must be Cargo project directory `{}`",
@@ -89,3 +179,19 @@ impl Cli {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use clap::Parser;
+
+	use super::Cli;
+
+	#[test]
+	fn poll_interval() {
+		let parse = |interval| {
+			Cli::try_parse_from(["hotbolt-runner", ".", "--poll-interval", interval])
+		};
+		assert_eq!(parse("500").unwrap().poll_interval, 500);
+		assert!(parse("0").is_err());
+	}
+}
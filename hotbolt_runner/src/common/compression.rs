@@ -0,0 +1,135 @@
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Prefix of tagged state, followed by the [`Compression`](Compression) tag.
+const MAGIC: &[u8; 3] = b"HBZ";
+
+/// Compression of the state sent between the server and client.
+///
+/// The server picks the compression and sends it to the client when it connects. State is tagged
+/// with its compression, even when uncompressed, so either side can decompress any state it
+/// receives. `lz4` and `zstd` require the cargo features of the same name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+	None,
+	Lz4,
+	Zstd,
+}
+
+impl Compression {
+	fn tag(self) -> u8 {
+		self as u8
+	}
+
+	fn from_tag(tag: u8) -> Option<Self> {
+		[Compression::None, Compression::Lz4, Compression::Zstd]
+			.iter()
+			.copied()
+			.find(|compression| compression.tag() == tag)
+	}
+
+	/// Returns the compression of the given state, along with the state without its tag.
+	///
+	/// Untagged state, such as a state file saved before states were tagged, is uncompressed.
+	pub fn split(state: &[u8]) -> (Self, &[u8]) {
+		match state {
+			[m1, m2, m3, tag, compressed @ ..] if [*m1, *m2, *m3] == *MAGIC => {
+				match Self::from_tag(*tag) {
+					Some(compression) => (compression, compressed),
+					None => (Compression::None, state),
+				}
+			}
+			_ => (Compression::None, state),
+		}
+	}
+
+	/// Compresses the state and tags it with the compression.
+	pub fn compress(self, state: &[u8]) -> Result<Box<[u8]>, String> {
+		let compressed = match self {
+			Compression::None => state.to_vec(),
+			#[cfg(feature = "lz4")]
+			Compression::Lz4 => lz4_flex::compress_prepend_size(state),
+			#[cfg(feature = "zstd")]
+			Compression::Zstd => zstd::bulk::compress(state, 0)
+				.map_err(|e| format!("Unable to compress state: {}", e))?,
+			#[allow(unreachable_patterns)]
+			compression => return Err(compression.unsupported()),
+		};
+		let mut tagged = Vec::with_capacity(MAGIC.len() + 1 + compressed.len());
+		tagged.extend_from_slice(MAGIC);
+		tagged.push(self.tag());
+		tagged.extend_from_slice(&compressed);
+		Ok(tagged.into_boxed_slice())
+	}
+
+	fn unsupported(self) -> String {
+		format!("Compression `{}` requires the `{}` feature of the runner", self, self)
+	}
+}
+
+/// Decompresses state compressed with any [`Compression`](Compression).
+pub fn decompress(state: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+	let (compression, compressed) = Compression::split(state);
+	let decompressed: Result<Vec<u8>, String> = match compression {
+		Compression::None => return Ok(Cow::Borrowed(compressed)),
+		#[cfg(feature = "lz4")]
+		Compression::Lz4 => {
+			lz4_flex::decompress_size_prepended(compressed).map_err(|e| e.to_string())
+		}
+		#[cfg(feature = "zstd")]
+		Compression::Zstd => zstd::stream::decode_all(compressed).map_err(|e| e.to_string()),
+		#[allow(unreachable_patterns)]
+		compression => Err(compression.unsupported()),
+	};
+	decompressed
+		.map(Cow::Owned)
+		.map_err(|e| format!("Unable to decompress state: {}", e))
+}
+
+impl FromStr for Compression {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"none" => Ok(Compression::None),
+			"lz4" if cfg!(feature = "lz4") => Ok(Compression::Lz4),
+			"zstd" if cfg!(feature = "zstd") => Ok(Compression::Zstd),
+			"lz4" | "zstd" => Err("requires the runner feature of the same name"),
+			_ => Err("no match"),
+		}
+	}
+}
+
+impl fmt::Display for Compression {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Compression::None => write!(f, "none"),
+			Compression::Lz4 => write!(f, "lz4"),
+			Compression::Zstd => write!(f, "zstd"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decompress, Compression};
+
+	#[test]
+	#[cfg(all(feature = "lz4", feature = "zstd"))]
+	fn tagged_roundtrip() {
+		// Uncompressed state that looks tagged is not mistaken for compressed state.
+		let state = b"HBZ\x02state";
+		for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+			let compressed = compression.compress(state).unwrap();
+			assert_eq!(Compression::split(&compressed).0, compression);
+			assert_eq!(&decompress(&compressed).unwrap()[..], state);
+		}
+	}
+
+	#[test]
+	fn untagged() {
+		assert_eq!(Compression::split(b"state"), (Compression::None, &b"state"[..]));
+		assert_eq!(&decompress(b"state").unwrap()[..], b"state");
+	}
+}
@@ -1,14 +1,72 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
+use super::Compression;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ServerMessage {
-	Start(Option<Box<[u8]>>),
+	Start(Option<Box<[u8]>>, LifecycleReason),
 	GetState,
-	Close,
+	/// Closes the client, once the library is told why it is unloaded.
+	Close(LifecycleReason),
+	AssetChanged(PathBuf),
+	BuildStatus(BuildStatus),
+	/// Requests the state as JSON.
+	GetJson,
+	/// Restarts the application with the state described by the JSON document.
+	SetJson(String),
+	/// Soft reloads the library copied to the given path, keeping the client process.
+	Reload(PathBuf),
+	/// The compression to use for the state sent to the server.
+	Compression(Compression),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ClientMessage {
 	Restart,
 	SetState(Option<Box<[u8]>>),
+	Watch(PathBuf),
+	/// The state as JSON, or why it could not be converted.
+	Json(Result<String, String>),
+	/// The library rejected the state for the given reason. The client waits to be restarted.
+	Rejected(String),
+}
+
+/// See [`hotbolt_ffi::BuildStatus`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BuildStatus {
+	Ok,
+	Building,
+	Failed(String),
+}
+
+impl From<BuildStatus> for hotbolt_ffi::BuildStatus {
+	fn from(status: BuildStatus) -> Self {
+		match status {
+			BuildStatus::Ok => hotbolt_ffi::BuildStatus::Ok,
+			BuildStatus::Building => hotbolt_ffi::BuildStatus::Building,
+			BuildStatus::Failed(summary) => hotbolt_ffi::BuildStatus::Failed(summary),
+		}
+	}
+}
+
+/// Why the client is started or closed. Soft reloads are handled by the client alone.
+///
+/// See [`hotbolt_ffi::base::LifecycleReason`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum LifecycleReason {
+	Startup,
+	HardRestart,
+	Shutdown,
+}
+
+impl From<LifecycleReason> for hotbolt_ffi::base::LifecycleReason {
+	fn from(reason: LifecycleReason) -> Self {
+		match reason {
+			LifecycleReason::Startup => hotbolt_ffi::base::LifecycleReason::Startup,
+			LifecycleReason::HardRestart => hotbolt_ffi::base::LifecycleReason::HardRestart,
+			LifecycleReason::Shutdown => hotbolt_ffi::base::LifecycleReason::Shutdown,
+		}
+	}
 }
@@ -1,5 +1,7 @@
 mod cli;
+mod compression;
 mod message;
 
 pub use cli::*;
+pub use compression::*;
 pub use message::*;
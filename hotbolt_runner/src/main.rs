@@ -1,3 +1,6 @@
+// `is_none_or`, suggested by unnecessary_map_or, is newer than the supported toolchains.
+#![allow(clippy::module_inception, clippy::unnecessary_map_or)]
+
 pub mod client;
 pub mod common;
 pub mod server;
@@ -28,6 +31,6 @@ fn main() {
 		server::start(lib_path, lib_path_normalized, &address, cli);
 	} else {
 		debug!("Starting client with: {:?}", &lib_path_normalized);
-		client::start(lib_path_normalized, &address);
+		client::start(lib_path_normalized, &address, cli.entry.as_deref());
 	}
 }
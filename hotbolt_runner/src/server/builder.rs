@@ -0,0 +1,80 @@
+use std::{
+	path::{Path, PathBuf},
+	process,
+	sync::mpsc::{Receiver, Sender},
+	thread,
+	time::Duration,
+};
+
+use log::{error, info};
+
+use crate::util::cargo::{self, CargoOptions};
+
+/// Time to wait for related source changes, such as an editor saving several files, before building.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+#[derive(Debug)]
+pub enum BuildEvent {
+	Started,
+	Succeeded,
+	Failed(String),
+}
+
+/// Rebuilds a Cargo project each time one of its sources changes.
+pub struct Builder {
+	dir: PathBuf,
+	options: CargoOptions,
+}
+
+impl Builder {
+	pub fn new(dir: impl AsRef<Path>, options: CargoOptions) -> Self {
+		Self {
+			dir: dir.as_ref().to_owned(),
+			options,
+		}
+	}
+
+	/// Builds the project each time a changed source path is received.
+	pub fn run(self, sources: Receiver<PathBuf>, sender: Sender<BuildEvent>) {
+		thread::spawn(move || {
+			let send = |event| {
+				if sender.send(event).is_err() {
+					error!("Unable to send build event");
+					process::exit(1);
+				}
+			};
+
+			while sources.recv().is_ok() {
+				thread::sleep(DEBOUNCE);
+				while sources.try_recv().is_ok() {}
+
+				info!("Building project...");
+				send(BuildEvent::Started);
+				match cargo::cargo_build(&self.dir, &self.options) {
+					Ok(()) => send(BuildEvent::Succeeded),
+					Err(diagnostics) => send(BuildEvent::Failed(diagnostics)),
+				}
+			}
+		});
+	}
+}
+
+/// Returns the first error of the compiler output, including its location when available.
+pub fn summary(diagnostics: &str) -> String {
+	let mut lines = diagnostics.lines().skip_while(|line| !line.starts_with("error"));
+	match (lines.next(), lines.next()) {
+		(Some(error), Some(location)) if location.trim_start().starts_with("-->") => {
+			format!("{} at {}", error, location.trim_start()[3..].trim())
+		}
+		(Some(error), _) => error.to_owned(),
+		(None, _) => "Build failed".to_owned(),
+	}
+}
+
+/// Logs the compiler output of a failed build.
+pub fn print_failure(diagnostics: &str) {
+	error!("Build failed. The current application keeps running.");
+	for line in diagnostics.lines() {
+		error!("{}", line);
+	}
+}
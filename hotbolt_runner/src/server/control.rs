@@ -0,0 +1,121 @@
+use std::{
+	fs,
+	io::{self, BufRead, BufReader, Write},
+	net::{TcpListener, TcpStream},
+	path::PathBuf,
+	str::FromStr,
+	sync::mpsc::{self, Sender},
+	thread,
+};
+
+use json_patch::Patch;
+use log::{debug, info};
+
+pub const HELP: &str = "\
+Commands:
+  snapshots            Lists the saved states, latest first
+  rewind <K>           Restarts the application with snapshot K
+  json                 Prints the state as JSON
+  json save <path>     Saves the state as JSON to the file
+  json set <json>      Restarts the application with the state of the JSON document
+  json patch <json>    Restarts the application with the state edited by the JSON Patch
+  help                 Shows this message
+
+JSON arguments starting with @ are read from the file with the given path.";
+
+/// Command sent by a user through the control socket.
+#[derive(Debug)]
+pub enum ControlCommand {
+	Help,
+	Snapshots,
+	Rewind(usize),
+	Json,
+	JsonSave(PathBuf),
+	JsonSet(String),
+	JsonPatch(Patch),
+}
+
+/// Returns the JSON argument, reading it from a file when prefixed with `@`.
+fn json_argument(argument: &str) -> Result<String, String> {
+	match argument.strip_prefix('@') {
+		Some(path) => fs::read_to_string(path)
+			.map_err(|e| format!("Unable to read JSON file `{}`: {}", path, e)),
+		None => Ok(argument.to_owned()),
+	}
+}
+
+impl FromStr for ControlCommand {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut words = s.trim().splitn(3, char::is_whitespace);
+		match (words.next(), words.next(), words.next()) {
+			(Some("help"), None, _) => Ok(ControlCommand::Help),
+			(Some("snapshots"), None, _) => Ok(ControlCommand::Snapshots),
+			(Some("rewind"), Some(index), None) => index
+				.parse()
+				.map(ControlCommand::Rewind)
+				.map_err(|_| format!("Invalid snapshot `{}`", index)),
+			(Some("json"), None, _) => Ok(ControlCommand::Json),
+			(Some("json"), Some("save"), Some(path)) => Ok(ControlCommand::JsonSave(path.into())),
+			(Some("json"), Some("set"), Some(json)) => {
+				json_argument(json).map(ControlCommand::JsonSet)
+			}
+			(Some("json"), Some("patch"), Some(json)) => {
+				let patch = serde_json::from_str(&json_argument(json)?)
+					.map_err(|e| format!("Invalid JSON Patch: {}", e))?;
+				Ok(ControlCommand::JsonPatch(patch))
+			}
+			_ => Err(format!("Unknown command `{}`. Use `help` to list commands", s.trim())),
+		}
+	}
+}
+
+/// A command along with the channel to send the reply on.
+pub struct ControlRequest {
+	pub command: ControlCommand,
+	pub reply: Sender<String>,
+}
+
+/// Accepts line-based commands on the given address, forwarding them to the sender.
+pub fn listen(address: &str, sender: Sender<ControlRequest>) -> Result<(), String> {
+	let listener = TcpListener::bind(address)
+		.map_err(|e| format!("Unable to start control socket on `{}`: {}", address, e))?;
+	info!("Listening for commands on `{}`", address);
+	thread::spawn(move || {
+		for stream in listener.incoming().flatten() {
+			let sender = sender.clone();
+			thread::spawn(move || {
+				if let Err(e) = handle(stream, sender) {
+					debug!("Control connection closed: {}", e);
+				}
+			});
+		}
+	});
+	Ok(())
+}
+
+fn handle(stream: TcpStream, sender: Sender<ControlRequest>) -> io::Result<()> {
+	let mut writer = stream.try_clone()?;
+	for line in BufReader::new(stream).lines() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let reply = match line.parse() {
+			Ok(command) => {
+				let (reply, receiver) = mpsc::channel();
+				if sender.send(ControlRequest { command, reply }).is_err() {
+					break;
+				}
+				receiver
+					.recv()
+					.unwrap_or_else(|_| "Client disconnected before replying".to_owned())
+			}
+			Err(e) => e,
+		};
+		writeln!(writer, "{}", reply)?;
+	}
+	Ok(())
+}
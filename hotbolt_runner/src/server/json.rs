@@ -0,0 +1,43 @@
+use std::{fs, path::PathBuf};
+
+use json_patch::Patch;
+use serde_json::Value;
+
+/// Control command waiting for the client to reply with the state as JSON.
+pub enum JsonRequest {
+	Print,
+	Save(PathBuf),
+	Patch(Patch),
+	/// Waits for the client to restart with the edited state instead.
+	Set,
+}
+
+/// What to do once the client replied with the state as JSON.
+pub enum JsonReply {
+	/// Replies to the user.
+	Done(String),
+	/// Restarts the client with the edited JSON document.
+	Edited(String),
+}
+
+fn parse(json: &str) -> Result<Value, String> {
+	serde_json::from_str(json).map_err(|e| format!("Client sent invalid JSON: {}", e))
+}
+
+impl JsonRequest {
+	pub fn complete(self, json: &str) -> Result<JsonReply, String> {
+		match self {
+			JsonRequest::Print => parse(json).map(|value| JsonReply::Done(value.to_string())),
+			JsonRequest::Save(path) => fs::write(&path, json)
+				.map(|_| JsonReply::Done(format!("Saved state to `{}`", path.display())))
+				.map_err(|e| format!("Unable to save state to `{}`: {}", path.display(), e)),
+			JsonRequest::Patch(patch) => {
+				let mut value = parse(json)?;
+				json_patch::patch(&mut value, &patch)
+					.map_err(|e| format!("Unable to apply JSON Patch: {}", e))?;
+				Ok(JsonReply::Edited(value.to_string()))
+			}
+			JsonRequest::Set => Err("Client sent JSON while restarting".to_owned()),
+		}
+	}
+}
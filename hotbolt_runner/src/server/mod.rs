@@ -1,4 +1,9 @@
+mod builder;
+mod control;
+mod json;
 mod server;
+mod snapshot;
+mod state_file;
 mod watcher;
 
 pub use server::*;
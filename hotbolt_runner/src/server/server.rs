@@ -1,27 +1,54 @@
 use std::{
+	collections::HashSet,
 	env, fs, io,
 	net::TcpListener,
-	path::Path,
+	path::{Path, PathBuf},
 	process::{self, Child, Command, Stdio},
-	sync::mpsc::{self, TryRecvError},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{self, Sender, TryRecvError},
+		Arc,
+	},
+	time::{Duration, Instant},
 };
 
-use log::{error, info};
+use hotbolt_ffi::ffi::StateEnvelope;
+use log::{debug, error, info, warn};
+use wait_timeout::ChildExt;
 
-use super::watcher::{self, notify::NotifyWatcher, poll::PollWatcher, Watcher};
+use super::{
+	builder::{self, BuildEvent, Builder},
+	control::{self, ControlCommand, ControlRequest},
+	json::{JsonReply, JsonRequest},
+	snapshot::Snapshots,
+	state_file,
+	watcher::{self, notify::NotifyWatcher, poll::PollWatcher, Watcher},
+};
 use crate::{
-	common::{ClientMessage, ServerMessage, WatcherType},
+	common::{
+		BuildStatus,
+		ClientMessage,
+		Compression,
+		LifecycleReason,
+		RejectedState,
+		ServerMessage,
+		WatcherType,
+	},
 	util::tcp,
 	Cli,
 };
 use tcp::TcpPeer;
 use watcher::WatcherEvent;
 
+/// How long to wait for the client to send its state before restarting without it.
+const STATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the client to run the unload hook of the library before killing it.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
 fn process_exit_code(process: &mut Child) -> Option<i32> {
-	if let Ok(status) = process.try_wait() {
-		if let Some(exit_code) = status {
-			return Some(exit_code.code().unwrap_or(1));
-		}
+	if let Ok(Some(exit_code)) = process.try_wait() {
+		return Some(exit_code.code().unwrap_or(1));
 	}
 	None
 }
@@ -34,7 +61,7 @@ fn send<'a>(
 	if let Err(e) = peer.write(request) {
 		error!("Error communicating with client: {}", e);
 		error!("Retarting client process...");
-		if let Err(_) = process.kill() {
+		if process.kill().is_err() {
 			error!("Unable to kill client process. Continuing...");
 		}
 		false
@@ -43,6 +70,78 @@ fn send<'a>(
 	}
 }
 
+/// Asks the client to close, so that the library is told why it is unloaded, then kills it if it
+/// does not exit in time.
+fn close<'a>(
+	peer: &mut TcpPeer<'a, ClientMessage, ServerMessage>,
+	process: &mut Child,
+	reason: LifecycleReason,
+) {
+	if process_exit_code(process).is_none() {
+		// The client may already be exiting, in which case it does not read this.
+		let _ = peer.write(ServerMessage::Close(reason));
+		if let Ok(Some(_)) = process.wait_timeout(CLOSE_TIMEOUT) {
+			return;
+		}
+		warn!("Client did not close in time. Killing it");
+	}
+	let _ = process.kill();
+	let _ = process.wait();
+}
+
+/// Writes the state to the state file, if any.
+fn save_state(cli: &Cli, state: Option<&[u8]>) {
+	if let Some(path) = &cli.state_file {
+		if let Err(e) = state_file::save(path, state) {
+			error!("{}", e);
+		}
+	}
+}
+
+fn log_state(state: Option<&[u8]>) {
+	match state {
+		Some(state) => match Compression::split(state) {
+			(Compression::None, state) => match StateEnvelope::decode(state) {
+				Some(envelope) => debug!(
+					"Saved state (version {}, {} bytes)",
+					envelope.version,
+					envelope.payload.len()
+				),
+				None => debug!("Saved unversioned state"),
+			},
+			(compression, _) => debug!(
+				"Saved state ({} bytes compressed with {})",
+				state.len(),
+				compression
+			),
+		},
+		None => debug!("Cleared state"),
+	}
+}
+
+/// Removes the library copies made for soft reloads, once the client using them is closed.
+fn remove_copies(copies: &mut Vec<PathBuf>) {
+	for copy in copies.drain(..) {
+		if let Err(e) = fs::remove_file(&copy) {
+			warn!("Unable to remove library copy `{}`: {}", copy.display(), e);
+		}
+	}
+}
+
+fn watch_assets(cli: &Cli, path: &Path, sender: Sender<PathBuf>) -> Result<(), String> {
+	let poll_watcher = PollWatcher::new(Duration::from_millis(cli.poll_interval), false);
+	match cli.watcher {
+		WatcherType::Poll => poll_watcher.run_assets(path, sender),
+		WatcherType::Notify => NotifyWatcher::new(false)
+			.run_assets(path, sender.clone())
+			.or_else(|e| {
+				warn!("{}", e);
+				warn!("Falling back to polling watcher");
+				poll_watcher.run_assets(path, sender)
+			}),
+	}
+}
+
 pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 	lib_path: P1,
 	lib_path_normalized: P2,
@@ -53,9 +152,17 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 
 	let watcher = match cli.watcher {
 		WatcherType::Poll => {
-			PollWatcher::new(std::time::Duration::from_secs(2)).run(&lib_path, watcher_sender)
+			PollWatcher::new(Duration::from_millis(cli.poll_interval), cli.content_hash)
+				.run(&lib_path, watcher_sender)
 		}
-		WatcherType::Notify => NotifyWatcher::new().run(&lib_path, watcher_sender),
+		WatcherType::Notify => NotifyWatcher::new(cli.content_hash)
+			.run(&lib_path, watcher_sender.clone())
+			.or_else(|e| {
+				warn!("{}", e);
+				warn!("Falling back to polling watcher");
+				PollWatcher::new(Duration::from_millis(cli.poll_interval), cli.content_hash)
+					.run(&lib_path, watcher_sender)
+			}),
 	};
 	if let Err(e) = watcher {
 		error!("{}", e);
@@ -68,9 +175,70 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 		error!("Unable to copy library file. Aborting");
 	}
 
+	let (asset_sender, asset_receiver) = mpsc::channel::<PathBuf>();
+	let mut watched_assets = HashSet::new();
+
+	let (build_sender, build_receiver) = mpsc::channel();
+	let mut build_status = BuildStatus::Ok;
+	if cli.build {
+		let (source_sender, source_receiver) = mpsc::channel();
+		let project = Path::new(&cli.input);
+		for source in &[project.join("src"), project.join("Cargo.toml")] {
+			if let Err(e) = watch_assets(&cli, source, source_sender.clone()) {
+				error!("{}", e);
+				error!("Unable to watch project sources. Aborting");
+				process::exit(1);
+			}
+		}
+		Builder::new(project, cli.cargo_options()).run(source_receiver, build_sender);
+	}
+
 	let mut app_state = None;
+	if let Some(path) = &cli.state_file {
+		app_state = state_file::load(path).unwrap_or_else(|e| {
+			error!("{}", e);
+			process::exit(1);
+		});
+		if app_state.is_some() {
+			info!("Restored state from `{}`", path.display());
+		}
+	}
+
+	let mut snapshots = Snapshots::new(cli.snapshots);
+	let mut start_reason = LifecycleReason::Startup;
+	let mut generation = 0;
+	let mut soft_copies = Vec::new();
+	// The snapshot restored after the library rejected the state, in case it is rejected too.
+	let mut rollback: Option<usize> = None;
+
+	let (control_sender, control_receiver) = mpsc::channel::<ControlRequest>();
+	if let Some(port) = &cli.control_port {
+		// The commands can read and write files, so they are only accepted from this machine.
+		if let Err(e) = control::listen(&format!("127.0.0.1:{}", port), control_sender) {
+			error!("{}", e);
+			process::exit(1);
+		}
+	}
+
+	let shutdown = Arc::new(AtomicBool::new(false));
+	let shutdown_handler = shutdown.clone();
+	let handler = ctrlc::set_handler(move || {
+		// Interrupting again skips waiting for the client.
+		if shutdown_handler.swap(true, Ordering::Relaxed) {
+			process::exit(1);
+		}
+	});
+	if let Err(e) = handler {
+		error!("Unable to handle interrupts: {}", e);
+		process::exit(1);
+	}
+
 	'spawn: loop {
-		let listener = match TcpListener::bind(&address) {
+		if shutdown.load(Ordering::Relaxed) {
+			process::exit(0);
+		}
+
+		let listener = match TcpListener::bind(address) {
 			Ok(listener) => listener,
 			Err(e) => {
 				error!("Unable to start TCP listener on `{}`: {}", address, e);
@@ -90,13 +258,31 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 				process::exit(1);
 			}
 		};
-		let command = Command::new(app)
+		let mut command = Command::new(app);
+		command
 			.env("RUST_LOG", "hotbolt_runner=debug")
 			.arg("--client")
 			.arg(&cli.input)
-			.args(&["--profile", &cli.profile])
-			.args(&["--host", &cli.host])
-			.args(&["--port", &cli.port])
+			.args(["--host", &cli.host])
+			.args(["--port", &cli.port]);
+		if cli.file {
+			command.arg("--file");
+		} else {
+			command.args(["--profile", &cli.profile]);
+		}
+		if let Some(target) = &cli.target {
+			command.args(["--target", target]);
+		}
+		if let Some(package) = &cli.package {
+			command.args(["--package", package]);
+		}
+		if let Some(example) = &cli.example {
+			command.args(["--example", example]);
+		}
+		if let Some(entry) = &cli.entry {
+			command.args(["--entry", entry]);
+		}
+		let command = command
 			.stdout(Stdio::inherit())
 			.stdin(Stdio::inherit())
 			.stderr(Stdio::inherit())
@@ -114,7 +300,7 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 		info!("Connecting to client...");
 		let mut incoming_iter = listener.incoming().peekable();
 		let stream = loop {
-			if let Some(_) = incoming_iter.peek() {
+			if incoming_iter.peek().is_some() {
 				if let Some(incoming) = incoming_iter.next() {
 					match incoming {
 						Ok(stream) => break stream,
@@ -131,6 +317,11 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 				}
 			}
 
+			if shutdown.load(Ordering::Relaxed) {
+				let _ = process.kill();
+				process::exit(0);
+			}
+
 			// In case client dies before we have a chance to reconnect.
 			if let Some(exit_code) = process_exit_code(&mut process) {
 				// We don't want to retry because it will probably happen again.
@@ -139,24 +330,72 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 			}
 		};
 
+		// Accepted streams do not inherit the non-blocking flag of the listener on all platforms.
+		if let Err(e) = stream.set_nonblocking(true) {
+			error!("Unable to use non-blocking client socket connection: {}", e);
+			process::exit(1);
+		}
+
 		info!("Connected");
 		let mut message_stream = TcpPeer::<ClientMessage, ServerMessage>::from(&stream);
+		// The compression is sent first, so that the client compresses all of its state.
 		if !send(
 			&mut message_stream,
 			&mut process,
-			ServerMessage::Start(app_state.clone()),
+			ServerMessage::Compression(cli.compression),
+		) || !send(
+			&mut message_stream,
+			&mut process,
+			ServerMessage::Start(app_state.clone(), start_reason),
+		) || !send(
+			&mut message_stream,
+			&mut process,
+			ServerMessage::BuildStatus(build_status.clone()),
 		) {
 			continue 'spawn;
 		}
+		start_reason = LifecycleReason::HardRestart;
 
 		let mut restarting = false;
 		let mut file_exists: bool = true;
+		let mut state_requested: Option<Instant> = None;
+		let mut shutting_down = false;
+		let mut library_changed = false;
+		let mut json_request = None;
 
 		loop {
+			if state_requested.is_some_and(|requested| requested.elapsed() > STATE_TIMEOUT) {
+				warn!("Client did not send its state in time. Using the last saved state");
+				state_requested = None;
+				restarting = true;
+			}
+
+			if shutdown.load(Ordering::Relaxed) && !shutting_down {
+				shutting_down = true;
+				if cli.state_file.is_some() {
+					info!("Saving state before shutting down...");
+					if !send(&mut message_stream, &mut process, ServerMessage::GetState) {
+						continue 'spawn;
+					}
+					state_requested.get_or_insert_with(Instant::now);
+				} else {
+					restarting = true;
+				}
+			}
+
+			if restarting && shutting_down {
+				close(&mut message_stream, &mut process, LifecycleReason::Shutdown);
+				remove_copies(&mut soft_copies);
+				info!("Shut down");
+				process::exit(0);
+			}
+
 			if restarting && file_exists {
-				// TODO: This if statement only works if the client is listening (which it might not be).
-				// We should kill after a timeout.
-				let _ = process.kill();
+				close(&mut message_stream, &mut process, LifecycleReason::HardRestart);
+				remove_copies(&mut soft_copies);
+				if library_changed {
+					generation += 1;
+				}
 
 				// TODO: This should live elsewhere.
 				if let Err(e) = fs::copy(&lib_path, &lib_path_normalized) {
@@ -166,7 +405,7 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 				// if !send(
 				// 	&mut message_stream,
 				// 	&mut process,
-				// 	ServerMessage::Start(app_state.clone()),
+				// 	ServerMessage::Start(app_state.clone(), start_reason),
 				// ) {
 				continue 'spawn;
 				// }
@@ -177,11 +416,34 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 					WatcherEvent::Created => {
 						file_exists = true;
 					}
+					WatcherEvent::Changed if cli.soft => {
+						// The loaded library cannot be overwritten, so each generation is copied.
+						let copy = lib_path_normalized
+							.as_ref()
+							.with_extension(format!("{}.hotbolt", generation + 1));
+						match fs::copy(&lib_path, &copy) {
+							Ok(_) => {
+								generation += 1;
+								soft_copies.push(copy.clone());
+								info!("Soft reloading library...");
+								let message = ServerMessage::Reload(copy);
+								if !send(&mut message_stream, &mut process, message) {
+									continue 'spawn;
+								}
+							}
+							Err(e) => {
+								error!("{}", e);
+								error!("Unable to copy library file. Skipping reload");
+							}
+						}
+					}
 					WatcherEvent::Changed => {
 						if !send(&mut message_stream, &mut process, ServerMessage::GetState) {
 							continue 'spawn;
 						}
-						restarting = true;
+						// The client is restarted once it sends its state.
+						state_requested.get_or_insert_with(Instant::now);
+						library_changed = true;
 					}
 					WatcherEvent::Destroyed => {
 						file_exists = false;
@@ -195,25 +457,158 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 				}
 			}
 
+			if let Ok(event) = build_receiver.try_recv() {
+				build_status = match event {
+					BuildEvent::Started => BuildStatus::Building,
+					BuildEvent::Succeeded => {
+						info!("Build succeeded");
+						BuildStatus::Ok
+					}
+					BuildEvent::Failed(diagnostics) => {
+						builder::print_failure(&diagnostics);
+						BuildStatus::Failed(builder::summary(&diagnostics))
+					}
+				};
+				if !send(
+					&mut message_stream,
+					&mut process,
+					ServerMessage::BuildStatus(build_status.clone()),
+				) {
+					continue 'spawn;
+				}
+			}
+
+			if let Ok(path) = asset_receiver.try_recv() {
+				debug!("Asset changed: {}", path.display());
+				if !send(
+					&mut message_stream,
+					&mut process,
+					ServerMessage::AssetChanged(path),
+				) {
+					continue 'spawn;
+				}
+			}
+
+			if let Ok(ControlRequest { command, reply }) = control_receiver.try_recv() {
+				// Commands needing the client are replied to once the client responds.
+				let deferred = match command {
+					ControlCommand::Help => Err(control::HELP.to_owned()),
+					ControlCommand::Snapshots => Err(snapshots.list()),
+					ControlCommand::Rewind(index) => Err(match snapshots.get(index) {
+						Some(snapshot) => {
+							info!("Restarting with snapshot {}", index);
+							app_state = Some(snapshot.state.clone());
+							save_state(&cli, app_state.as_deref());
+							restarting = true;
+							format!("Restarting with snapshot {}", index)
+						}
+						None => format!("No snapshot {}", index),
+					}),
+					_ if json_request.is_some() => {
+						Err("Another JSON command is in progress".to_owned())
+					}
+					ControlCommand::Json => Ok((ServerMessage::GetJson, JsonRequest::Print)),
+					ControlCommand::JsonSave(path) => {
+						Ok((ServerMessage::GetJson, JsonRequest::Save(path)))
+					}
+					ControlCommand::JsonSet(json) => {
+						Ok((ServerMessage::SetJson(json), JsonRequest::Set))
+					}
+					ControlCommand::JsonPatch(patch) => {
+						Ok((ServerMessage::GetJson, JsonRequest::Patch(patch)))
+					}
+				};
+				match deferred {
+					Ok((message, request)) => {
+						if !send(&mut message_stream, &mut process, message) {
+							continue 'spawn;
+						}
+						json_request = Some((request, reply));
+					}
+					Err(response) => {
+						let _ = reply.send(response);
+					}
+				}
+			}
+
 			match message_stream.try_read() {
 				Ok(maybe_message) => {
 					if let Some(message) = maybe_message {
 						match message {
 							ClientMessage::Restart => {
+								if let Some((JsonRequest::Set, reply)) = json_request.take() {
+									info!("Restarting with edited state");
+									let _ = reply.send("Restarting with edited state".to_owned());
+								}
 								restarting = true;
-								if !send(&mut message_stream, &mut process, ServerMessage::Close) {
-									continue 'spawn;
+							}
+							ClientMessage::Rejected(reason) => {
+								error!("The library rejected the state: {}", reason);
+								let snapshot = match cli.rejected_state {
+									RejectedState::Fresh => None,
+									RejectedState::Rollback => {
+										let index = rollback.map_or_else(
+											|| {
+												app_state
+													.as_deref()
+													.and_then(|state| snapshots.position(state))
+													.map_or(0, |index| index + 1)
+											},
+											|index| index + 1,
+										);
+										snapshots.get(index).map(|snapshot| (index, snapshot))
+									}
+								};
+								if let Some((index, snapshot)) = snapshot {
+									info!("Rolling back to snapshot {}", index);
+									app_state = Some(snapshot.state.clone());
+									rollback = Some(index);
+								} else {
+									info!("Restarting with new state");
+									app_state = None;
+									rollback = None;
 								}
+								save_state(&cli, app_state.as_deref());
+								restarting = true;
 							}
 							ClientMessage::SetState(client_state) => {
+								rollback = None;
+								log_state(client_state.as_deref());
+								if let Some(state) = &client_state {
+									snapshots.push(state.clone(), generation);
+								}
 								app_state = client_state;
-								if restarting {
-									if !send(
-										&mut message_stream,
-										&mut process,
-										ServerMessage::Close,
-									) {
-										continue 'spawn;
+								save_state(&cli, app_state.as_deref());
+								if state_requested.take().is_some() {
+									restarting = true;
+								}
+							}
+							ClientMessage::Json(json) => {
+								if let Some((request, reply)) = json_request.take() {
+									match json.and_then(|json| request.complete(&json)) {
+										Ok(JsonReply::Done(response)) => {
+											let _ = reply.send(response);
+										}
+										Ok(JsonReply::Edited(json)) => {
+											let message = ServerMessage::SetJson(json);
+											if !send(&mut message_stream, &mut process, message) {
+												continue 'spawn;
+											}
+											json_request = Some((JsonRequest::Set, reply));
+										}
+										Err(e) => {
+											let _ = reply.send(e);
+										}
+									}
+								}
+							}
+							ClientMessage::Watch(path) => {
+								// Clients register their assets again each time they are spawned.
+								if watched_assets.insert(path.clone()) {
+									info!("Watching assets: {}", path.display());
+									if let Err(e) = watch_assets(&cli, &path, asset_sender.clone()) {
+										error!("{}", e);
+										watched_assets.remove(&path);
 									}
 								}
 							}
@@ -226,7 +621,7 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 							info!("Process exited with code: {}", exit_code);
 						} else {
 							error!("Client connection lost: {}", e);
-							if let Err(_) = process.kill() {
+							if process.kill().is_err() {
 								error!("Unable to kill client process. Continuing...");
 							}
 						}
@@ -241,6 +636,4 @@ pub fn start<P1: AsRef<Path>, P2: AsRef<Path>>(
 			}
 		}
 	}
-
-	// TODO: Gracefully shut down client.
 }
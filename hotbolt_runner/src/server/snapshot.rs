@@ -0,0 +1,82 @@
+use std::{
+	collections::VecDeque,
+	fmt::Write,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// State received from the client.
+pub struct Snapshot {
+	pub state: Box<[u8]>,
+	pub time: SystemTime,
+	/// The number of times the library changed before the state was saved.
+	pub generation: u32,
+}
+
+/// Ring buffer of the latest snapshots.
+pub struct Snapshots {
+	snapshots: VecDeque<Snapshot>,
+	capacity: usize,
+}
+
+impl Snapshots {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			snapshots: VecDeque::with_capacity(capacity),
+			capacity,
+		}
+	}
+
+	/// Saves the state, dropping the oldest snapshot when full.
+	pub fn push(&mut self, state: Box<[u8]>, generation: u32) {
+		if self.capacity == 0 {
+			return;
+		}
+		if self.snapshots.len() == self.capacity {
+			self.snapshots.pop_back();
+		}
+		self.snapshots.push_front(Snapshot {
+			state,
+			time: SystemTime::now(),
+			generation,
+		});
+	}
+
+	/// Returns the snapshot at the given index, where 0 is the latest.
+	pub fn get(&self, index: usize) -> Option<&Snapshot> {
+		self.snapshots.get(index)
+	}
+
+	/// Returns the index of the latest snapshot of the given state.
+	pub fn position(&self, state: &[u8]) -> Option<usize> {
+		self.snapshots
+			.iter()
+			.position(|snapshot| &*snapshot.state == state)
+	}
+
+	/// Returns a line for each snapshot, latest first.
+	pub fn list(&self) -> String {
+		if self.snapshots.is_empty() {
+			return "No snapshots".to_owned();
+		}
+		let mut list = String::new();
+		for (index, snapshot) in self.snapshots.iter().enumerate() {
+			let seconds = snapshot
+				.time
+				.duration_since(UNIX_EPOCH)
+				.map(|duration| duration.as_secs())
+				.unwrap_or_default();
+			let _ = writeln!(
+				list,
+				"{}: {:02}:{:02}:{:02} UTC, generation {}, {} bytes",
+				index,
+				seconds / 3600 % 24,
+				seconds / 60 % 60,
+				seconds % 60,
+				snapshot.generation,
+				snapshot.state.len()
+			);
+		}
+		list.pop();
+		list
+	}
+}
@@ -0,0 +1,42 @@
+use std::{
+	ffi::OsString,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+/// Returns the path the state is written to before replacing the state file.
+fn temporary_path(path: &Path) -> PathBuf {
+	let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+	file_name.push(".tmp");
+	path.with_file_name(file_name)
+}
+
+/// Reads the state saved by a previous session, if any.
+pub fn load(path: &Path) -> Result<Option<Box<[u8]>>, String> {
+	match fs::read(path) {
+		Ok(state) => Ok(Some(state.into_boxed_slice())),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(format!("Unable to read state file `{}`: {}", path.display(), e)),
+	}
+}
+
+/// Atomically replaces the saved state, removing the state file when the state was cleared.
+pub fn save(path: &Path, state: Option<&[u8]>) -> Result<(), String> {
+	let result = match state {
+		Some(state) => {
+			let temporary_path = temporary_path(path);
+			fs::File::create(&temporary_path)
+				.and_then(|mut file| {
+					file.write_all(state)?;
+					file.sync_all()
+				})
+				.and_then(|_| fs::rename(&temporary_path, path))
+		}
+		None => match fs::remove_file(path) {
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+			result => result,
+		},
+	};
+	result.map_err(|e| format!("Unable to write state file `{}`: {}", path.display(), e))
+}
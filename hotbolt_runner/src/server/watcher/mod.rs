@@ -1,9 +1,16 @@
-use std::{path::Path, sync::mpsc::Sender};
+use std::{
+	collections::hash_map::DefaultHasher,
+	fs,
+	hash::{Hash, Hasher},
+	io,
+	path::{Path, PathBuf},
+	sync::mpsc::Sender,
+};
 
 pub mod notify;
 pub mod poll;
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum WatcherEvent {
 	Created,
 	Changed,
@@ -12,4 +19,45 @@ pub enum WatcherEvent {
 
 pub trait Watcher {
 	fn run(&self, filepath: impl AsRef<Path>, sender: Sender<WatcherEvent>) -> Result<(), String>;
+
+	/// Watches a file or every file under a directory, sending the path of each changed file.
+	fn run_assets(&self, path: impl AsRef<Path>, sender: Sender<PathBuf>) -> Result<(), String>;
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+	let content = fs::read(path)?;
+	let mut hasher = DefaultHasher::new();
+	content.hash(&mut hasher);
+	Ok(hasher.finish())
+}
+
+/// Remembers the content of a file to filter out changes that did not modify it (e.g. `touch`).
+pub struct ContentHash {
+	path: PathBuf,
+	last: Option<u64>,
+}
+
+impl ContentHash {
+	pub fn new(path: impl AsRef<Path>) -> Self {
+		let path = path.as_ref().to_owned();
+		let last = hash_file(&path).ok();
+		Self { path, last }
+	}
+
+	/// Returns true if the content differs from the last time it was checked.
+	///
+	/// Unreadable files are always considered changed so that errors are reported downstream.
+	pub fn changed(&mut self) -> bool {
+		match hash_file(&self.path) {
+			Ok(hash) => {
+				let changed = self.last != Some(hash);
+				self.last = Some(hash);
+				changed
+			}
+			Err(_) => {
+				self.last = None;
+				true
+			}
+		}
+	}
 }
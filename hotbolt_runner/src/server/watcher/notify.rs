@@ -1,19 +1,56 @@
-use std::{path::Path, process, sync::mpsc::Sender};
+use std::{
+	ffi::OsStr,
+	path::{Path, PathBuf},
+	process,
+	sync::mpsc::Sender,
+};
 
 use log::{debug, error, info};
 
 use notify::{
-	event::{Event, EventKind},
+	event::{Event, EventKind, ModifyKind, RenameMode},
 	RecommendedWatcher, RecursiveMode, Watcher,
 };
 
-use super::WatcherEvent;
+use super::{ContentHash, WatcherEvent};
 
-pub struct NotifyWatcher;
+pub struct NotifyWatcher {
+	content_hash: bool,
+}
 
 impl NotifyWatcher {
-	pub fn new() -> Self {
-		Self
+	/// Creates a watcher using the native filesystem events. With `content_hash`, modifications are
+	/// only reported when the file content differs.
+	pub fn new(content_hash: bool) -> Self {
+		Self { content_hash }
+	}
+}
+
+/// Converts a filesystem event from the watched directory into runner events for the given file.
+///
+/// Linkers and build tools often write to a temporary file and rename it into place, so a rename
+/// onto the file is treated as a fresh library.
+fn runner_events(event: &Event, file_name: &OsStr) -> &'static [WatcherEvent] {
+	let is_file = |path: &PathBuf| path.file_name() == Some(file_name);
+	match event.kind {
+		EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+			if event.paths.get(1).is_some_and(is_file) {
+				&[WatcherEvent::Created, WatcherEvent::Changed]
+			} else if event.paths.first().is_some_and(is_file) {
+				&[WatcherEvent::Destroyed]
+			} else {
+				&[]
+			}
+		}
+		_ if !event.paths.iter().any(is_file) => &[],
+		EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+			&[WatcherEvent::Created, WatcherEvent::Changed]
+		}
+		EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+			&[WatcherEvent::Destroyed]
+		}
+		EventKind::Modify(_) => &[WatcherEvent::Changed],
+		_ => &[],
 	}
 }
 
@@ -23,29 +60,39 @@ impl super::Watcher for NotifyWatcher {
 		if !path.is_file() {
 			return Err(format!("Input `{}` must be a file", path.display()));
 		}
-		let dir = path.parent().ok_or_else(|| {
-			return format!("Failed to get path directory of `{}`", path.display());
-		})?;
+		let file_name = path
+			.file_name()
+			.ok_or_else(|| format!("Failed to get file name of `{}`", path.display()))?
+			.to_owned();
+		let dir = match path.parent() {
+			Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+			Some(dir) => dir,
+			None => return Err(format!("Failed to get path directory of `{}`", path.display())),
+		};
 
 		debug!("Started with library path: {}", path.display());
 
+		let mut content_hash = if self.content_hash {
+			Some(ContentHash::new(path))
+		} else {
+			None
+		};
+
 		let watcher_result: Result<RecommendedWatcher, _> =
 			Watcher::new(move |res: Result<Event, _>| match res {
 				Ok(event) => {
-					let runner_event = match event.kind {
-						EventKind::Create(_) => {
-							info!("File was created");
-							Some(WatcherEvent::Created)
+					for runner_event in runner_events(&event, &file_name) {
+						match runner_event {
+							WatcherEvent::Created => info!("File was created"),
+							WatcherEvent::Changed => {
+								if !content_hash.as_mut().map_or(true, ContentHash::changed) {
+									debug!("Library was touched without changing its content");
+									continue;
+								}
+							}
+							WatcherEvent::Destroyed => info!("File was removed"),
 						}
-						EventKind::Modify(_) => Some(WatcherEvent::Changed),
-						EventKind::Remove(_) => {
-							info!("File was removed");
-							Some(WatcherEvent::Destroyed)
-						}
-						_ => None,
-					};
-					if let Some(runner_event) = runner_event {
-						if sender.send(runner_event).is_err() {
+						if sender.send(*runner_event).is_err() {
 							error!("Unable to send runner event");
 							process::exit(1);
 						}
@@ -57,13 +104,97 @@ impl super::Watcher for NotifyWatcher {
 				}
 			});
 
-		if let Ok(mut watcher) = watcher_result {
-			match watcher.watch(dir, RecursiveMode::NonRecursive) {
-				Ok(_) => Ok(()),
-				Err(_) => Err("Failed to attach filesystem watcher to file".to_string()),
-			}
-		} else {
-			Err("Failed to instaniate filesystem watcher".to_string())
+		let mut watcher = watcher_result
+			.map_err(|_| "Failed to instantiate filesystem watcher".to_string())?;
+		watcher
+			.watch(dir, RecursiveMode::NonRecursive)
+			.map_err(|_| "Failed to attach filesystem watcher to file".to_string())?;
+
+		// Like the polling thread, the watcher lives for the rest of the process.
+		Box::leak(Box::new(watcher));
+		Ok(())
+	}
+
+	fn run_assets(&self, path: impl AsRef<Path>, sender: Sender<PathBuf>) -> Result<(), String> {
+		let path = path.as_ref();
+		if !path.exists() {
+			return Err(format!("Asset path `{}` does not exist", path.display()));
 		}
+
+		let watcher_result: Result<RecommendedWatcher, _> =
+			Watcher::new(move |res: Result<Event, _>| match res {
+				Ok(event) => {
+					if let EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) =
+						event.kind
+					{
+						for path in event.paths {
+							if sender.send(path).is_err() {
+								error!("Unable to send asset event");
+								process::exit(1);
+							}
+						}
+					}
+				}
+				Err(_) => {
+					error!("Filesystem watcher error. Aborting");
+					process::exit(1);
+				}
+			});
+
+		let mut watcher = watcher_result
+			.map_err(|_| "Failed to instantiate filesystem watcher".to_string())?;
+		watcher
+			.watch(path, RecursiveMode::Recursive)
+			.map_err(|_| format!("Failed to attach filesystem watcher to `{}`", path.display()))?;
+
+		Box::leak(Box::new(watcher));
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::ffi::OsStr;
+
+	use notify::event::{CreateKind, DataChange, Event, EventKind, ModifyKind, RenameMode};
+
+	use super::{runner_events, WatcherEvent};
+
+	const LIBRARY: &str = "libapp.so";
+
+	fn events(kind: EventKind, files: &[&str]) -> &'static [WatcherEvent] {
+		let event = files.iter().fold(Event::new(kind), |event, file| {
+			event.add_path(format!("target/debug/{}", file).into())
+		});
+		runner_events(&event, OsStr::new(LIBRARY))
+	}
+
+	fn rename(mode: RenameMode) -> EventKind {
+		EventKind::Modify(ModifyKind::Name(mode))
+	}
+
+	#[test]
+	fn rename_onto_library() {
+		assert_eq!(
+			events(rename(RenameMode::Both), &["libapp.so.tmp", LIBRARY]),
+			[WatcherEvent::Created, WatcherEvent::Changed]
+		);
+		assert_eq!(
+			events(rename(RenameMode::To), &[LIBRARY]),
+			[WatcherEvent::Created, WatcherEvent::Changed]
+		);
+		assert_eq!(
+			events(rename(RenameMode::Both), &[LIBRARY, "libapp.so.old"]),
+			[WatcherEvent::Destroyed]
+		);
+	}
+
+	#[test]
+	fn sibling_files() {
+		let modify = || EventKind::Modify(ModifyKind::Data(DataChange::Any));
+		assert!(events(rename(RenameMode::Both), &["libapp.so.tmp", "libapp.d"]).is_empty());
+		assert!(events(EventKind::Create(CreateKind::File), &["libapp.d"]).is_empty());
+		assert!(events(modify(), &["libapp.so.tmp"]).is_empty());
+		assert_eq!(events(modify(), &[LIBRARY]), [WatcherEvent::Changed]);
 	}
 }
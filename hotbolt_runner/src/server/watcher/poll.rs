@@ -1,16 +1,45 @@
-use std::{path::Path, process, sync::mpsc::Sender, thread, time::Duration};
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+	process,
+	sync::mpsc::Sender,
+	thread,
+	time::{Duration, SystemTime},
+};
 
-use log::error;
+use log::{debug, error};
 
-use super::WatcherEvent;
+use super::{ContentHash, WatcherEvent};
 
 pub struct PollWatcher {
 	interval: Duration,
+	content_hash: bool,
 }
 
 impl PollWatcher {
-	pub fn new(interval: Duration) -> Self {
-		Self { interval }
+	/// Creates a watcher checking every `interval`. With `content_hash`, modifications are only
+	/// reported when the file content differs.
+	pub fn new(interval: Duration, content_hash: bool) -> Self {
+		Self {
+			interval,
+			content_hash,
+		}
+	}
+}
+
+/// Collects the last modified time of the given file or every file under the given directory.
+fn modified_times(path: &Path, times: &mut HashMap<PathBuf, SystemTime>) {
+	if let Ok(metadata) = path.metadata() {
+		if metadata.is_dir() {
+			if let Ok(entries) = fs::read_dir(path) {
+				for entry in entries.flatten() {
+					modified_times(&entry.path(), times);
+				}
+			}
+		} else if let Ok(modified_time) = metadata.modified() {
+			times.insert(path.to_owned(), modified_time);
+		}
 	}
 }
 
@@ -31,8 +60,12 @@ impl super::Watcher for PollWatcher {
 			)
 		})?;
 
-		// TODO: Don't clone interval.
-		let interval = self.interval.clone();
+		let interval = self.interval;
+		let mut content_hash = if self.content_hash {
+			Some(ContentHash::new(&path))
+		} else {
+			None
+		};
 		thread::spawn(move || {
 			let mut last_modified = Some(last_modified_time);
 			loop {
@@ -41,7 +74,12 @@ impl super::Watcher for PollWatcher {
 						if let Some(last_modified_time) = last_modified {
 							if last_modified_time != modified_time {
 								last_modified = Some(modified_time);
-								Some(WatcherEvent::Changed)
+								if content_hash.as_mut().map_or(true, ContentHash::changed) {
+									Some(WatcherEvent::Changed)
+								} else {
+									debug!("Library was touched without changing its content");
+									None
+								}
 							} else {
 								None
 							}
@@ -69,4 +107,36 @@ impl super::Watcher for PollWatcher {
 		});
 		Ok(())
 	}
+
+	fn run_assets(&self, path: impl AsRef<Path>, sender: Sender<PathBuf>) -> Result<(), String> {
+		let path = path.as_ref().to_owned();
+		if !path.exists() {
+			return Err(format!("Asset path `{}` does not exist", path.display()));
+		}
+
+		let interval = self.interval;
+		let mut last_modified = HashMap::new();
+		modified_times(&path, &mut last_modified);
+		thread::spawn(move || loop {
+			thread::sleep(interval);
+
+			let mut modified = HashMap::new();
+			modified_times(&path, &mut modified);
+			let changed = modified
+				.iter()
+				.filter(|(file, time)| last_modified.get(*file) != Some(time))
+				.map(|(file, _)| file);
+			let removed = last_modified
+				.keys()
+				.filter(|file| !modified.contains_key(*file));
+			for file in changed.chain(removed) {
+				if sender.send(file.clone()).is_err() {
+					error!("Unable to send asset event");
+					process::exit(1);
+				}
+			}
+			last_modified = modified;
+		});
+		Ok(())
+	}
 }
@@ -3,9 +3,11 @@ mod reload;
 
 use std::{future::Future, io, sync::Once, time::Duration};
 
+use hotbolt_ffi::ffi;
 use log::info;
 
 use project::{Builder, Project};
+use reload::control;
 
 static INIT: Once = Once::new();
 
@@ -31,7 +33,7 @@ async fn test<R: Future<Output = io::Result<()>>>(
 	result
 }
 
-const TEST_HARD_REBUILD_CODE_BEFORE: &'static str = "
+const TEST_HARD_REBUILD_CODE_BEFORE: &str = "
 use hotbolt::hotbolt_entry_main;
 #[hotbolt_entry_main]
 fn main() {
@@ -40,9 +42,9 @@ fn main() {
 }
 ";
 
-const TEST_HARD_REBUILD_OUT_BEFORE: &'static str = "Hello world!";
+const TEST_HARD_REBUILD_OUT_BEFORE: &str = "Hello world!";
 
-const TEST_HARD_RELOAD_CODE_AFTER: &'static str = "
+const TEST_HARD_RELOAD_CODE_AFTER: &str = "
 use hotbolt::hotbolt_entry_main;
 #[hotbolt_entry_main]
 fn main() {
@@ -51,7 +53,7 @@ fn main() {
 }
 ";
 
-const TEST_HARD_RELOAD_OUT_AFTER: &'static str = "Hello hot reload!";
+const TEST_HARD_RELOAD_OUT_AFTER: &str = "Hello hot reload!";
 
 #[tokio::test]
 async fn test_hard_rebuild() -> io::Result<()> {
@@ -79,7 +81,7 @@ async fn hard_rebuild(project: Project) -> io::Result<()> {
 	result
 }
 
-const TEST_HARD_MANUAL_CODE_BEFORE: &'static str = "
+const TEST_HARD_MANUAL_CODE_BEFORE: &str = "
 use hotbolt::{hotbolt_entry_main, Server};
 #[hotbolt_entry_main]
 fn main(server: impl Server) {
@@ -89,9 +91,9 @@ fn main(server: impl Server) {
 }
 ";
 
-const TEST_HARD_MANUAL_OUT_BEFORE: &'static str = "Hello world!";
+const TEST_HARD_MANUAL_OUT_BEFORE: &str = "Hello world!";
 
-const TEST_HARD_MANUAL_CODE_AFTER: &'static str = "
+const TEST_HARD_MANUAL_CODE_AFTER: &str = "
 use hotbolt::{hotbolt_entry_main, Server};
 #[hotbolt_entry_main]
 fn main(server: impl Server) {
@@ -101,7 +103,7 @@ fn main(server: impl Server) {
 }
 ";
 
-const TEST_HARD_MANUAL_OUT_AFTER: &'static str = "Hello hot reload!";
+const TEST_HARD_MANUAL_OUT_AFTER: &str = "Hello hot reload!";
 
 // TODO: Ignore this test until we fix it.
 #[tokio::test]
@@ -133,6 +135,1620 @@ async fn hard_manual(project: Project) -> io::Result<()> {
 	result
 }
 
+// Prints once per start, so a restart caused by touching the library shows up.
+const TEST_CONTENT_HASH_CODE: &str = "
+use hotbolt::{hotbolt_entry_main, Server};
+
+#[hotbolt_entry_main]
+fn main(_server: impl Server) {
+	println!(\"Start: {greeting}\");
+	loop {
+		std::thread::sleep(std::time::Duration::from_secs(1));
+	}
+}
+";
+
+const TEST_CONTENT_HASH_OUT_BEFORE: &str = "Start: Hello";
+
+const TEST_CONTENT_HASH_OUT_TOUCHED: &str = "Start:";
+
+const TEST_CONTENT_HASH_OUT_AFTER: &str = "Start: Bonjour";
+
+#[tokio::test]
+async fn test_content_hash() -> io::Result<()> {
+	test("content_hash", content_hash).await
+}
+
+async fn content_hash(project: Project) -> io::Result<()> {
+	project.update(&TEST_CONTENT_HASH_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--poll-interval")
+		.arg("100")
+		.arg("--content-hash")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_CONTENT_HASH_OUT_BEFORE)
+		.await?;
+	let result = async {
+		// Only the modification time changes, which must not restart the application.
+		let library = project.path(&format!(
+			"target/debug/{}content_hash{}",
+			std::env::consts::DLL_PREFIX,
+			std::env::consts::DLL_SUFFIX
+		));
+		std::fs::OpenOptions::new()
+			.write(true)
+			.open(library)?
+			.set_modified(std::time::SystemTime::now())?;
+		reload.expect_none(TEST_CONTENT_HASH_OUT_TOUCHED, Duration::from_secs(2)).await?;
+
+		project.update(&TEST_CONTENT_HASH_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_CONTENT_HASH_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_ASSET_CODE: &str = "
+use hotbolt::{hotbolt_entry_main, Server};
+#[hotbolt_entry_main]
+fn main(server: impl Server) {
+	server.watch(\"{asset}\");
+	std::thread::sleep(std::time::Duration::from_secs(1));
+	println!(\"Watching assets\");
+	loop {
+		if let Some(path) = server.asset_changed() {
+			println!(\"Asset changed: {}\", path);
+		}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_ASSET_OUT_BEFORE: &str = "Watching assets";
+
+const TEST_ASSET_OUT_AFTER: &str = "Asset changed:";
+
+#[tokio::test]
+async fn test_asset_watch() -> io::Result<()> {
+	test("asset_watch", asset_watch).await
+}
+
+async fn asset_watch(project: Project) -> io::Result<()> {
+	let asset = project.write("assets/level.txt", "1")?;
+	let asset_dir = asset.parent().unwrap().display().to_string();
+	project.update(&TEST_ASSET_CODE.replace("{asset}", &asset_dir.replace('\\', "\\\\")))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_ASSET_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.write("assets/level.txt", "2")?;
+		reload.expect(TEST_ASSET_OUT_AFTER).await?;
+		Ok(())
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_BUILD_FAILURE_CODE_BEFORE: &str = "
+use hotbolt::{hotbolt_entry_main, BuildStatus, Server};
+#[hotbolt_entry_main]
+fn main(server: impl Server) {
+	println!(\"Hello world!\");
+	loop {
+		if let BuildStatus::Failed(summary) = server.build_status() {
+			println!(\"Build failed: {}\", summary);
+			break;
+		}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+	loop {}
+}
+";
+
+const TEST_BUILD_FAILURE_OUT_BEFORE: &str = "Hello world!";
+
+const TEST_BUILD_FAILURE_CODE_AFTER: &str = "
+use hotbolt::hotbolt_entry_main;
+#[hotbolt_entry_main]
+fn main() {
+	let value: u8 = \"Hello hot reload!\";
+}
+";
+
+const TEST_BUILD_FAILURE_OUT_AFTER: &str = "Build failed: error[E0308]: mismatched types at src/lib.rs";
+
+#[tokio::test]
+async fn test_build_failure() -> io::Result<()> {
+	test("build_failure", build_failure).await
+}
+
+async fn build_failure(project: Project) -> io::Result<()> {
+	project.update(TEST_BUILD_FAILURE_CODE_BEFORE)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--build")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_BUILD_FAILURE_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(TEST_BUILD_FAILURE_CODE_AFTER)?;
+		reload.expect(TEST_BUILD_FAILURE_OUT_AFTER).await?;
+		Ok(())
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_CODE: &str = "
+use std::ffi::c_void;
+use hotbolt::{
+	base::{BaseRun, Deserializer, Migration, Serializer, ServerBase, StateConverter},
+	ffi::{self, FfiRun, FfiState, FfiStringVisitor, StateRestore},
+	prelude::UnsafeFrom,
+	FfiArray, FfiArrayMut,
+};
+
+#[derive(Default)]
+struct Counter(u32);
+
+struct Converter;
+
+impl Serializer<Counter> for Converter {
+	fn serialize(value: &Counter) -> Result<FfiArrayMut<'static, u8>, ()> {
+		let bytes = format!(\"{prefix}{}\", value.0).into_bytes();
+		Ok(unsafe { FfiArrayMut::unsafe_from(bytes) })
+	}
+}
+
+impl Deserializer<Counter> for Converter {
+	fn deserialize(bytes: &[u8]) -> Result<Counter, ()> {
+		let text = std::str::from_utf8(bytes).map_err(|_| ())?;
+		let count = text.strip_prefix(\"{prefix}\").ok_or(())?;
+		count.parse().map(Counter).map_err(|_| ())
+	}
+}
+
+impl StateConverter for Converter {
+	type State = Counter;
+	type Serializer = Self;
+	type Deserializer = Self;
+	const MIGRATIONS: &'static [Migration] = &[{migrations}];
+}
+
+struct Main;
+
+impl BaseRun for Main {
+	type StateConverter = Converter;
+
+	fn run(_server: impl ServerBase<Counter>, state: &mut Counter) {
+		state.0 += 1;
+		{print}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+
+#[unsafe(no_mangle)]
+pub extern \"C\" fn hotbolt_entry_server_version() -> u8 {
+	ffi::SERVER_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub extern \"C\" fn hotbolt_entry_run(app: *mut c_void, server: ffi::FfiServer, state: *mut c_void) {
+	<Main as FfiRun>::run(app, server, state)
+}
+
+#[unsafe(no_mangle)]
+pub extern \"C\" fn hotbolt_entry_state_new(
+	serialized: FfiArray<'static, u8>,
+	restore: *mut StateRestore,
+	out: *mut c_void,
+	visit: FfiStringVisitor,
+) -> *mut c_void {
+	Converter::state_new(serialized, restore, out, visit)
+}
+
+#[unsafe(no_mangle)]
+pub extern \"C\" fn hotbolt_entry_state_serialize_new(state: *const c_void) -> FfiArrayMut<'static, u8> {
+	Converter::state_serialized_new(state)
+}
+
+#[unsafe(no_mangle)]
+pub extern \"C\" fn hotbolt_entry_state_serialize_drop(serialized: FfiArrayMut<'static, u8>) {
+	Converter::state_serialized_drop(serialized)
+}
+";
+
+const TEST_STATE_MIGRATION_OUT_BEFORE: &str = "Count: 5";
+
+// Adds a prefix to the serialized count and bumps it, so that migrated state stands out.
+const TEST_STATE_MIGRATION_MIGRATE: &str = "|bytes| {
+	let count: u32 = std::str::from_utf8(bytes).map_err(|_| ())?.parse().map_err(|_| ())?;
+	Ok(format!(\"count={}\", count + 1000).into_bytes())
+}";
+
+const TEST_STATE_MIGRATION_OUT_AFTER: &str = "Migrated count";
+
+#[tokio::test]
+async fn test_state_migration() -> io::Result<()> {
+	test("state_migration", state_migration).await
+}
+
+async fn state_migration(project: Project) -> io::Result<()> {
+	project.update(
+		&TEST_STATE_CODE
+			.replace("{prefix}", "")
+			.replace("{migrations}", "")
+			.replace("{print}", "println!(\"Count: {}\", state.0);"),
+	)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_MIGRATION_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(
+			&TEST_STATE_CODE
+				.replace("{prefix}", "count=")
+				.replace("{migrations}", TEST_STATE_MIGRATION_MIGRATE)
+				.replace("{print}", "if state.0 > 1000 { println!(\"Migrated count\"); }"),
+		)?;
+		project.build()?;
+		reload.expect(TEST_STATE_MIGRATION_OUT_AFTER).await?;
+		Ok(())
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_FILE_OUT_BEFORE: &str = "Count: 5";
+
+const TEST_STATE_FILE_OUT_AFTER: &str = "Kept count";
+
+#[tokio::test]
+async fn test_state_file() -> io::Result<()> {
+	test("state_file", state_file).await
+}
+
+async fn state_file(project: Project) -> io::Result<()> {
+	let code = TEST_STATE_CODE
+		.replace("{prefix}", "")
+		.replace("{migrations}", "");
+	project.update(&code.replace("{print}", "println!(\"Count: {}\", state.0);"))?;
+	project.build()?;
+
+	let state_file = project.path("state.bin").display().to_string();
+	let mut reload = project
+		.hot_reload()
+		.arg("--state-file")
+		.arg(&state_file)
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_FILE_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&code.replace("{print}", "if state.0 > 5 { println!(\"Kept count\"); }"))?;
+		project.build()?;
+		reload.expect(TEST_STATE_FILE_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+	result?;
+
+	// The state of the previous session is restored from the file.
+	let reload = project
+		.hot_reload()
+		.arg("--state-file")
+		.arg(&state_file)
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_FILE_OUT_AFTER)
+		.await;
+	reload?.take().kill().await
+}
+
+const TEST_STATE_COMPRESSION_OUT_BEFORE: &str = "Count: 5";
+
+const TEST_STATE_COMPRESSION_OUT_AFTER: &str = "Kept count";
+
+#[tokio::test]
+async fn test_state_compression() -> io::Result<()> {
+	test("state_compression", state_compression).await
+}
+
+async fn state_compression(project: Project) -> io::Result<()> {
+	let code = TEST_STATE_CODE
+		.replace("{prefix}", "")
+		.replace("{migrations}", "");
+	project.update(&code.replace("{print}", "println!(\"Count: {}\", state.0);"))?;
+	project.build()?;
+
+	let state_file = project.path("state.bin");
+	let hot_reload = || {
+		project
+			.hot_reload()
+			.arg("--compression")
+			.arg("zstd")
+			.arg("--state-file")
+			.arg(&state_file.display().to_string())
+			.timeout(Duration::from_secs(60))
+	};
+	let mut reload = hot_reload().expect(TEST_STATE_COMPRESSION_OUT_BEFORE).await?;
+	let result = async {
+		project.update(&code.replace("{print}", "if state.0 > 5 { println!(\"Kept count\"); }"))?;
+		project.build()?;
+		reload.expect(TEST_STATE_COMPRESSION_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+	result?;
+
+	// The state is saved compressed, and restored by the next session.
+	if !std::fs::read(&state_file)?.starts_with(b"HBZ\x02") {
+		return Err(io::Error::other("State file is not compressed"));
+	}
+	let reload = hot_reload().expect(TEST_STATE_COMPRESSION_OUT_AFTER).await;
+	reload?.take().kill().await
+}
+
+const TEST_SNAPSHOT_REWIND_CONTROL_PORT: &str = "49200";
+
+const TEST_SNAPSHOT_REWIND_OUT_BEFORE: &str = "Count: 5";
+
+const TEST_SNAPSHOT_REWIND_OUT_AFTER: &str = "Count: 20.";
+
+const TEST_SNAPSHOT_REWIND_OUT_REWOUND: &str = "Count: 6.";
+
+#[tokio::test]
+async fn test_snapshot_rewind() -> io::Result<()> {
+	test("snapshot_rewind", snapshot_rewind).await
+}
+
+async fn snapshot_rewind(project: Project) -> io::Result<()> {
+	let code = TEST_STATE_CODE
+		.replace("{prefix}", "")
+		.replace("{migrations}", "");
+	// Stops counting at 5, so that the snapshot taken on reload is predictable.
+	project.update(&code.replace(
+		"{print}",
+		"state.0 = state.0.min(5); println!(\"Count: {}\", state.0);",
+	))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--control-port")
+		.arg(TEST_SNAPSHOT_REWIND_CONTROL_PORT)
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_SNAPSHOT_REWIND_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&code.replace("{print}", "println!(\"Count: {}.\", state.0);"))?;
+		project.build()?;
+		reload.expect(TEST_SNAPSHOT_REWIND_OUT_AFTER).await?;
+
+		let snapshots = control(TEST_SNAPSHOT_REWIND_CONTROL_PORT, "snapshots").await?;
+		if !snapshots.starts_with("0: ") || !snapshots.contains("generation 0") {
+			return Err(io::Error::other(format!("Unexpected snapshots: {}", snapshots)));
+		}
+		control(TEST_SNAPSHOT_REWIND_CONTROL_PORT, "rewind 0").await?;
+		reload.expect(TEST_SNAPSHOT_REWIND_OUT_REWOUND).await?;
+		Ok(())
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_JSON_CODE: &str = "
+use hotbolt::{
+	base::{BaseRun, ServerBase},
+	converter::SerdeBincode,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Counter {
+	count: u32,
+}
+
+type Converter = SerdeBincode<Counter>;
+
+#[hotbolt::main(json)]
+struct Main;
+
+impl BaseRun for Main {
+	type StateConverter = Converter;
+
+	fn run(_server: impl ServerBase<Counter>, state: &mut Counter) {
+		if state.count < 5 {
+			state.count += 1;
+		}
+		println!(\"Count: {}\", state.count);
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_STATE_JSON_CONTROL_PORT: &str = "49201";
+
+const TEST_STATE_JSON_OUT_BEFORE: &str = "Count: 5";
+
+const TEST_STATE_JSON_STATE: &str = "{\"count\":5}";
+
+const TEST_STATE_JSON_PATCH: &str = "json patch [{\"op\":\"replace\",\"path\":\"/count\",\"value\":100}]";
+
+const TEST_STATE_JSON_OUT_AFTER: &str = "Count: 100";
+
+#[tokio::test]
+async fn test_state_json() -> io::Result<()> {
+	test("state_json", state_json).await
+}
+
+async fn state_json(project: Project) -> io::Result<()> {
+	project.dependencies(
+		&["serde"],
+		&["serde = { version = \"1\", features = [\"derive\"] }"],
+	)?;
+	project.update(TEST_STATE_JSON_CODE)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--control-port")
+		.arg(TEST_STATE_JSON_CONTROL_PORT)
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_JSON_OUT_BEFORE)
+		.await?;
+	let result = async {
+		let state = control(TEST_STATE_JSON_CONTROL_PORT, "json").await?;
+		if state != TEST_STATE_JSON_STATE {
+			return Err(io::Error::other(format!("Unexpected state: {}", state)));
+		}
+		control(TEST_STATE_JSON_CONTROL_PORT, TEST_STATE_JSON_PATCH).await?;
+		reload.expect(TEST_STATE_JSON_OUT_AFTER).await?;
+		Ok(())
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_FIELDS_CODE: &str = "
+use hotbolt::{
+	base::{BaseRun, ServerBase},
+	HotboltState,
+};
+
+#[derive(HotboltState)]
+struct Counter {
+	count: u32,
+	{fields}
+}
+
+#[hotbolt::main]
+struct Main;
+
+impl BaseRun for Main {
+	type StateConverter = Counter;
+
+	fn run(_server: impl ServerBase<Counter>, state: &mut Counter) {
+		if state.count < 5 {
+			state.count += 1;
+		}
+		{print}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_STATE_FIELDS_OUT_BEFORE: &str = "Count: 5";
+
+// Renames `name`, drops `legacy` and adds `added`, which should keep the remaining fields.
+const TEST_STATE_FIELDS_AFTER: &str = "
+	#[hotbolt(alias = \"name\")]
+	label: String,
+	added: Vec<u8>,
+";
+
+const TEST_STATE_FIELDS_OUT_AFTER: &str = "Fields: 5, kept, []";
+
+#[tokio::test]
+async fn test_state_fields() -> io::Result<()> {
+	test("state_fields", state_fields).await
+}
+
+async fn state_fields(project: Project) -> io::Result<()> {
+	project.update(
+		&TEST_STATE_FIELDS_CODE
+			.replace("{fields}", "name: String, legacy: u8,")
+			.replace(
+				"{print}",
+				"state.name = String::from(\"kept\"); println!(\"Count: {}\", state.count);",
+			),
+	)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_FIELDS_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(
+			&TEST_STATE_FIELDS_CODE
+				.replace("{fields}", TEST_STATE_FIELDS_AFTER)
+				.replace(
+					"{print}",
+					"println!(\"Fields: {}, {}, {:?}\", state.count, state.label, state.added);",
+				),
+		)?;
+		project.build()?;
+		reload.expect(TEST_STATE_FIELDS_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_VALIDATION_OUT_BEFORE: &str = "Count: 5";
+
+const TEST_STATE_VALIDATION_OUT_CHANGED: &str = "Count: 20";
+
+// Rejects the latest state, so that the runner rolls back to the state before it.
+const TEST_STATE_VALIDATION_CHECK: &str = "
+fn check(state: &Counter) -> Result<(), String> {
+	if state.count > 10 {
+		Err(format!(\"Count {} is too high\", state.count))
+	} else {
+		Ok(())
+	}
+}
+
+#[hotbolt::main]
+";
+
+// Prints the first count only, which is 1 when restarted with new state instead.
+const TEST_STATE_VALIDATION_PRINT: &str = "
+	static FIRST: std::sync::Once = std::sync::Once::new();
+	FIRST.call_once(|| println!(\"Validated count: {}.\", state.count));
+";
+
+const TEST_STATE_VALIDATION_OUT_AFTER: &str = "Validated count: 5.";
+
+#[tokio::test]
+async fn test_state_validation() -> io::Result<()> {
+	test("state_validation", state_validation).await
+}
+
+async fn state_validation(project: Project) -> io::Result<()> {
+	let code = TEST_STATE_FIELDS_CODE.replace("{fields}", "");
+	project.update(&code.replace("{print}", "println!(\"Count: {}\", state.count);"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--rejected-state")
+		.arg("rollback")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_VALIDATION_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&code.replace(
+			"{print}",
+			"state.count = 20; println!(\"Count: {}\", state.count);",
+		))?;
+		project.build()?;
+		reload.expect(TEST_STATE_VALIDATION_OUT_CHANGED).await?;
+
+		project.update(
+			&code
+				.replace(
+					"#[derive(HotboltState)]",
+					"#[derive(HotboltState)]\n#[hotbolt(validate = \"check\")]",
+				)
+				.replace("#[hotbolt::main]", TEST_STATE_VALIDATION_CHECK)
+				.replace("{print}", TEST_STATE_VALIDATION_PRINT),
+		)?;
+		project.build()?;
+		reload.expect(TEST_STATE_VALIDATION_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_LAYOUT_CODE: &str = "
+use hotbolt::{
+	base::{BaseRun, Deserializer, Serializer, ServerBase, StateConverter},
+	FfiArrayMut, StateLayout,
+};
+
+#[derive(Default, StateLayout)]
+struct Counter {
+	count: u32,
+	{fields}
+}
+
+// Loses the state when serializing, so that the count is only kept when the state is reused.
+struct Converter;
+
+impl Serializer<Counter> for Converter {
+	fn serialize(_value: &Counter) -> Result<FfiArrayMut<'static, u8>, ()> {
+		Ok(FfiArrayMut::empty())
+	}
+}
+
+impl Deserializer<Counter> for Converter {
+	fn deserialize(_bytes: &[u8]) -> Result<Counter, ()> {
+		Err(())
+	}
+}
+
+impl StateConverter for Converter {
+	type State = Counter;
+	type Serializer = Self;
+	type Deserializer = Self;
+}
+
+#[hotbolt::main(layout)]
+struct Main;
+
+impl BaseRun for Main {
+	type StateConverter = Converter;
+
+	fn run(_server: impl ServerBase<Counter>, state: &mut Counter) {
+		if state.count < 5 {
+			state.count += 1;
+		}
+		{print}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_STATE_LAYOUT_OUT_BEFORE: &str = "Count: 5";
+
+const TEST_STATE_LAYOUT_OUT_REUSED: &str = "Reused count: 5";
+
+const TEST_STATE_LAYOUT_OUT_CHANGED: &str = "Changed count: 1";
+
+#[tokio::test]
+async fn test_state_layout() -> io::Result<()> {
+	test("state_layout", state_layout).await
+}
+
+async fn state_layout(project: Project) -> io::Result<()> {
+	let code = TEST_STATE_LAYOUT_CODE.replace("{fields}", "");
+	project.update(&code.replace("{print}", "println!(\"Count: {}\", state.count);"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--soft")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_LAYOUT_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&code.replace("{print}", "println!(\"Reused count: {}\", state.count);"))?;
+		project.build()?;
+		reload.expect(TEST_STATE_LAYOUT_OUT_REUSED).await?;
+
+		// Adding a field changes the layout, so the state is serialized and lost.
+		project.update(
+			&TEST_STATE_LAYOUT_CODE
+				.replace("{fields}", "extra: u8,")
+				.replace("{print}", "println!(\"Changed count: {}\", state.count);"),
+		)?;
+		project.build()?;
+		reload.expect(TEST_STATE_LAYOUT_OUT_CHANGED).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+// Plain statics are only reset by restarting the client, so the runs count across soft restarts.
+const TEST_SOFT_RESTART_CODE: &str = "
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hotbolt::{
+	base::{Run, Server},
+	HotboltState,
+};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(HotboltState)]
+struct Counter {
+	count: u32,
+}
+
+#[hotbolt::main]
+struct Main;
+
+impl Run for Main {
+	type StateConverter = Counter;
+	type App = ();
+	type AppVersion = ();
+
+	fn run(_app: &mut (), server: impl Server<Counter>, state: &mut Counter) {
+		let runs = RUNS.fetch_add(1, Ordering::Relaxed) + 1;
+		state.count += 1;
+		println!(\"Runs: {}, count: {}.\", runs, state.count);
+		match runs {
+			2 => server.restart_soft(),
+			4 => server.restart_soft_with(Box::new(Counter { count: 10 })),
+			_ => {}
+		}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_SOFT_RESTART_OUT_BEFORE: &str = "Runs: 2, count: 2.";
+
+const TEST_SOFT_RESTART_OUT_NEW: &str = "Runs: 3, count: 1.";
+
+const TEST_SOFT_RESTART_OUT_WITH: &str = "Runs: 5, count: 11.";
+
+#[tokio::test]
+async fn test_soft_restart() -> io::Result<()> {
+	test("soft_restart", soft_restart).await
+}
+
+async fn soft_restart(project: Project) -> io::Result<()> {
+	project.update(TEST_SOFT_RESTART_CODE)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_SOFT_RESTART_OUT_BEFORE)
+		.await?;
+	let result = async {
+		reload.expect(TEST_SOFT_RESTART_OUT_NEW).await?;
+		reload.expect(TEST_SOFT_RESTART_OUT_WITH).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+const TEST_STATE_ARCHIVE_CODE: &str = "
+use hotbolt::{
+	archive::{Archived, Relocatable},
+	base::{BaseRun, ServerBase},
+	StateLayout,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, StateLayout)]
+struct Tile {
+	height: u32,
+	kind: u32,
+}
+
+unsafe impl Relocatable for Tile {}
+
+#[hotbolt::main]
+struct Main;
+
+impl BaseRun for Main {
+	type StateConverter = Archived<Tile>;
+
+	fn run(_server: impl ServerBase<Archived<Tile>>, tiles: &mut Archived<Tile>) {
+		{print}
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_STATE_ARCHIVE_PRINT_BEFORE: &str = "
+	if tiles.is_empty() {
+		tiles.to_mut().extend((0..100_000).map(|height| Tile { height, kind: 1 }));
+	}
+	println!(\"Tiles: {}\", tiles.len());
+";
+
+const TEST_STATE_ARCHIVE_OUT_BEFORE: &str = "Tiles: 100000";
+
+// Only reads the tiles, so that they stay borrowed from the serialized state.
+const TEST_STATE_ARCHIVE_PRINT_AFTER: &str = "
+	let last = tiles[tiles.len() - 1].height;
+	println!(\"Archived tiles: {}, {}, borrowed: {}\", tiles.len(), last, tiles.is_borrowed());
+";
+
+const TEST_STATE_ARCHIVE_OUT_AFTER: &str = "Archived tiles: 100000, 99999, borrowed: true";
+
+#[tokio::test]
+async fn test_state_archive() -> io::Result<()> {
+	test("state_archive", state_archive).await
+}
+
+async fn state_archive(project: Project) -> io::Result<()> {
+	project.update(&TEST_STATE_ARCHIVE_CODE.replace("{print}", TEST_STATE_ARCHIVE_PRINT_BEFORE))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_ARCHIVE_OUT_BEFORE)
+		.await?;
+	let result = async {
+		let code = TEST_STATE_ARCHIVE_CODE.replace("{print}", TEST_STATE_ARCHIVE_PRINT_AFTER);
+		project.update(&code)?;
+		project.build()?;
+		reload.expect(TEST_STATE_ARCHIVE_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_RELOADABLE_CODE: &str = "
+use hotbolt::{
+	archive::Archived,
+	base::{App, AppVersion, Run, Server},
+};
+
+#[hotbolt::reloadable]
+fn greeting() -> &'static str {
+	\"{greeting}\"
+}
+
+struct Printer;
+
+impl App for Printer {
+	// Started by the first build only, which keeps running its own code.
+	fn new() -> Self {
+		std::thread::spawn(|| loop {
+			println!(\"Greeting: {}\", greeting());
+			std::thread::sleep(std::time::Duration::from_millis(100));
+		});
+		Printer
+	}
+}
+
+impl AppVersion for Printer {
+	type T = str;
+
+	fn version() -> &'static str {
+		\"1\"
+	}
+}
+
+#[hotbolt::main]
+struct Main;
+
+impl Run for Main {
+	type StateConverter = Archived<u8>;
+	type App = Printer;
+	type AppVersion = Printer;
+
+	fn run(_app: &mut Printer, _server: impl Server<Archived<u8>>, _state: &mut Archived<u8>) {
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_RELOADABLE_OUT_BEFORE: &str = "Greeting: Hello";
+
+const TEST_RELOADABLE_OUT_AFTER: &str = "Greeting: Bonjour";
+
+#[tokio::test]
+async fn test_reloadable() -> io::Result<()> {
+	test("reloadable", reloadable).await
+}
+
+async fn reloadable(project: Project) -> io::Result<()> {
+	project.update(&TEST_RELOADABLE_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--soft")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_RELOADABLE_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&TEST_RELOADABLE_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_RELOADABLE_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_STATE_MUT_CODE: &str = "
+use hotbolt::{fields::FieldValue, hotbolt_entry_main, HotboltState, Server};
+
+#[derive(HotboltState)]
+struct Counter {
+	count: u32,
+}
+
+#[hotbolt_entry_main]
+fn main(server: impl Server, state: &mut Counter) {
+	println!(\"Count: {}.\", state.count);
+	state.count += 1;
+	let mut bytes = Vec::new();
+	state.encode(&mut bytes);
+	std::thread::sleep(std::time::Duration::from_millis(200));
+	server.reload_with(&bytes);
+}
+";
+
+const TEST_STATE_MUT_OUT_BEFORE: &str = "Count: 0.";
+
+const TEST_STATE_MUT_OUT_AFTER: &str = "Count: 2.";
+
+#[tokio::test]
+async fn test_state_mut() -> io::Result<()> {
+	test("state_mut", state_mut).await
+}
+
+async fn state_mut(project: Project) -> io::Result<()> {
+	project.update(TEST_STATE_MUT_CODE)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATE_MUT_OUT_BEFORE)
+		.await?;
+	let result = reload.expect(TEST_STATE_MUT_OUT_AFTER).await;
+	reload.take().kill().await?;
+
+	result
+}
+
+// Every misuse is reported at line 5, where the entry point is declared.
+const TEST_MAIN_DIAGNOSTICS_CODE: &str = "
+use hotbolt::{hotbolt_entry_main, Server};
+
+#[hotbolt_entry_main]
+{item}
+";
+
+const TEST_MAIN_DIAGNOSTICS: &[(&str, &str)] = &[
+	("struct Main;", "#[hotbolt_entry_main] is intended on a function"),
+	("fn main(_count: u32) {}", "expected `impl Server`, `&[u8]` or `&mut State`"),
+	("fn main(_a: impl Server, _b: impl Server) {}", "duplicate argument of the same type"),
+	("fn main(_bytes: &[u8], _count: &mut u32) {}", "the state can only be taken once"),
+	("fn main(_count: &mut u32) {}", "`u32: StateConverter` is not satisfied"),
+];
+
+#[tokio::test]
+async fn test_main_diagnostics() -> io::Result<()> {
+	test("main_diagnostics", main_diagnostics).await
+}
+
+async fn main_diagnostics(project: Project) -> io::Result<()> {
+	for (item, message) in TEST_MAIN_DIAGNOSTICS {
+		project.update(&TEST_MAIN_DIAGNOSTICS_CODE.replace("{item}", item))?;
+		let output = project.build_error()?;
+		if !output.contains(message) || !output.contains("src/lib.rs:5:") {
+			return Err(io::Error::other(format!("Expected `{}` at line 5: {}", message, output)));
+		}
+	}
+	Ok(())
+}
+
+const TEST_ASYNC_MAIN_CODE: &str = "
+use std::time::Duration;
+
+use hotbolt::{hotbolt_entry_main, Server};
+
+#[hotbolt_entry_main]
+async fn main(_server: impl Server) {
+	hotbolt::runtime::spawn(async {
+		loop {
+			println!(\"Task: {greeting}\");
+			tokio::time::sleep(Duration::from_millis(100)).await;
+		}
+	});
+	loop {
+		println!(\"Main: {greeting}\");
+		tokio::time::sleep(Duration::from_millis(100)).await;
+	}
+}
+";
+
+const TEST_ASYNC_MAIN_OUT_BEFORE: &str = "Main: Hello";
+
+// Only printed once the task was spawned again by the new build.
+const TEST_ASYNC_MAIN_OUT_AFTER: &str = "Task: Bonjour";
+
+#[tokio::test]
+async fn test_async_main() -> io::Result<()> {
+	test("async_main", async_main).await
+}
+
+async fn async_main(project: Project) -> io::Result<()> {
+	project.dependencies(&["tokio"], &["tokio = { version = \"1\", features = [\"time\"] }"])?;
+	project.update(&TEST_ASYNC_MAIN_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--soft")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_ASYNC_MAIN_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&TEST_ASYNC_MAIN_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_ASYNC_MAIN_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+// Plain statics are only reset by restarting the client, so the runs count across reloads.
+const TEST_ASYNC_RELOAD_CODE: &str = "
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hotbolt::{hotbolt_entry_main, Server};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[hotbolt_entry_main]
+async fn main(server: impl Server) {
+	let runs = RUNS.fetch_add(1, Ordering::Relaxed) + 1;
+	println!(\"Runs: {}.\", runs);
+	match runs {
+		1 => server.reload(),
+		2 => server.reload_with(&[]),
+		_ => {}
+	}
+	tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+}
+";
+
+const TEST_ASYNC_RELOAD_OUT: &str = "Runs: 3.";
+
+#[tokio::test]
+async fn test_async_reload() -> io::Result<()> {
+	test("async_reload", async_reload).await
+}
+
+async fn async_reload(project: Project) -> io::Result<()> {
+	project.dependencies(&["tokio"], &["tokio = { version = \"1\", features = [\"time\"] }"])?;
+	project.update(TEST_ASYNC_RELOAD_CODE)?;
+	project.build()?;
+
+	let reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_ASYNC_RELOAD_OUT)
+		.await?;
+	reload.take().kill().await?;
+
+	Ok(())
+}
+
+// Only exports the run entry point, without a manifest or state.
+const TEST_STATELESS_CODE: &str = "
+use hotbolt::{
+	internal::{FfiServer, SizedCharArray},
+	Server,
+};
+
+#[unsafe(no_mangle)]
+pub extern \"C\" fn hotbolt_entry_run(server: FfiServer, _state: SizedCharArray) {
+	println!(\"Started\");
+	std::thread::sleep(std::time::Duration::from_millis(500));
+	server.reload();
+	loop {
+		std::thread::sleep(std::time::Duration::from_secs(1));
+	}
+}
+";
+
+const TEST_STATELESS_OUT: &str = "Started";
+
+#[tokio::test]
+async fn test_stateless() -> io::Result<()> {
+	test("stateless", stateless).await
+}
+
+async fn stateless(project: Project) -> io::Result<()> {
+	project.update(TEST_STATELESS_CODE)?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_STATELESS_OUT)
+		.await?;
+	// Reloading saves no state instead of failing to read it.
+	let result = reload.expect(TEST_STATELESS_OUT).await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_UNVERSIONED_APP_CODE: &str = "
+use hotbolt::{
+	archive::Archived,
+	base::{App, Run, Server},
+};
+
+struct Greeter;
+
+impl App for Greeter {
+	fn new() -> Self {
+		println!(\"App: {greeting}\");
+		Greeter
+	}
+}
+
+#[hotbolt::main]
+struct Main;
+
+impl Run for Main {
+	type StateConverter = Archived<u8>;
+	type App = Greeter;
+	type AppVersion = ();
+
+	fn run(_app: &mut Greeter, _server: impl Server<Archived<u8>>, _state: &mut Archived<u8>) {
+		std::thread::sleep(std::time::Duration::from_millis(100));
+	}
+}
+";
+
+const TEST_UNVERSIONED_APP_OUT_BEFORE: &str = "App: Hello";
+
+// Only printed if the app was created again, since soft reloading keeps it.
+const TEST_UNVERSIONED_APP_OUT_AFTER: &str = "App: Bonjour";
+
+#[tokio::test]
+async fn test_unversioned_app() -> io::Result<()> {
+	test("unversioned_app", unversioned_app).await
+}
+
+async fn unversioned_app(project: Project) -> io::Result<()> {
+	project.update(&TEST_UNVERSIONED_APP_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--soft")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_UNVERSIONED_APP_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&TEST_UNVERSIONED_APP_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_UNVERSIONED_APP_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_NAMED_ENTRY_CODE: &str = "
+use hotbolt::{
+	archive::Archived,
+	base::{BaseRun, ServerBase},
+	hotbolt_entry_main,
+	Server,
+};
+
+#[hotbolt_entry_main(name = \"editor\")]
+fn editor(_server: impl Server) {
+	println!(\"Editor: {greeting}\");
+	std::thread::sleep(std::time::Duration::from_secs(1));
+}
+
+#[hotbolt::main(name = \"server\")]
+struct Main;
+
+impl BaseRun for Main {
+	type StateConverter = Archived<u8>;
+
+	fn run(_server: impl ServerBase<Archived<u8>>, _state: &mut Archived<u8>) {
+		println!(\"Server: {greeting}\");
+		std::thread::sleep(std::time::Duration::from_secs(1));
+	}
+}
+";
+
+const TEST_NAMED_ENTRY_OUT_BEFORE: &str = "Server: Hello";
+
+const TEST_NAMED_ENTRY_OUT_AFTER: &str = "Server: Bonjour";
+
+#[tokio::test]
+async fn test_named_entry() -> io::Result<()> {
+	test("named_entry", named_entry).await
+}
+
+async fn named_entry(project: Project) -> io::Result<()> {
+	project.update(&TEST_NAMED_ENTRY_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--entry")
+		.arg("server")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_NAMED_ENTRY_OUT_BEFORE)
+		.await?;
+	// The same entry is run again after reloading.
+	let result = async {
+		project.update(&TEST_NAMED_ENTRY_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_NAMED_ENTRY_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+// Exported in debug builds, without declaring the `hotbolt_erase` feature.
+const TEST_ERASE_IF_CODE: &str = "
+use hotbolt::{hotbolt_entry_main, Server};
+
+#[hotbolt_entry_main(erase_if = \"not(debug_assertions)\")]
+fn main(_server: impl Server) {
+	println!(\"Not erased\");
+	std::thread::sleep(std::time::Duration::from_secs(1));
+}
+";
+
+const TEST_ERASE_IF_OUT: &str = "Not erased";
+
+#[tokio::test]
+async fn test_erase_if() -> io::Result<()> {
+	test("erase_if", erase_if).await
+}
+
+async fn erase_if(project: Project) -> io::Result<()> {
+	project.update(TEST_ERASE_IF_CODE)?;
+	project.build()?;
+
+	let reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_ERASE_IF_OUT)
+		.await?;
+	reload.take().kill().await?;
+
+	Ok(())
+}
+
+// Always erased, so that the library is left with nothing for the runner to bind.
+const TEST_ERASED_CODE: &str = "
+use hotbolt::{hotbolt_entry_main, Server};
+
+hotbolt::persistent! {
+	static RUNS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+}
+
+#[hotbolt::reloadable(erase_if = \"all()\")]
+fn greeting() -> &'static str {
+	\"Hello\"
+}
+
+#[hotbolt_entry_main(erase_if = \"all()\")]
+pub fn main(_server: impl Server) {
+	RUNS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+	println!(\"{}\", greeting());
+}
+";
+
+#[tokio::test]
+async fn test_erased() -> io::Result<()> {
+	test("erased", erased).await
+}
+
+async fn erased(project: Project) -> io::Result<()> {
+	project.update(TEST_ERASED_CODE)?;
+	project.build()?;
+
+	let library = project.path(&format!(
+		"target/debug/{}erased{}",
+		std::env::consts::DLL_PREFIX,
+		std::env::consts::DLL_SUFFIX
+	));
+	let lib = libloading::Library::new(library).map_err(io::Error::other)?;
+	for symbol in [
+		ffi::ENTRY_APP_RUN,
+		ffi::ENTRY_RELOADABLE_BIND,
+		ffi::ENTRY_PERSISTENT_BIND,
+	] {
+		let exported = unsafe { lib.get::<*const ()>(symbol.as_bytes()) }.is_ok();
+		assert!(!exported, "erased library exports `{}`", symbol);
+	}
+
+	Ok(())
+}
+
+// Keeps the first greeting it ran with, while printing the current one.
+const TEST_PERSISTENT_HARD_CODE: &str = "
+use std::sync::Mutex;
+
+use hotbolt::{hotbolt_entry_main, Server};
+
+hotbolt::persistent! {
+	static FIRST: Mutex<String> = Mutex::new(String::new());
+}
+
+#[hotbolt_entry_main]
+fn main(_server: impl Server) {
+	let mut first = FIRST.lock().unwrap();
+	if first.is_empty() {
+		*first = \"{greeting}\".to_owned();
+	}
+	println!(\"First: {}, current: {greeting}\", first);
+	drop(first);
+	loop {
+		std::thread::sleep(std::time::Duration::from_secs(1));
+	}
+}
+";
+
+const TEST_PERSISTENT_OUT_BEFORE: &str = "First: Hello, current: Hello";
+
+const TEST_PERSISTENT_OUT_AFTER: &str = "First: Hello, current: Bonjour";
+
+#[tokio::test]
+async fn test_persistent_hard() -> io::Result<()> {
+	test("persistent_hard", persistent_hard).await
+}
+
+async fn persistent_hard(project: Project) -> io::Result<()> {
+	project.update(&TEST_PERSISTENT_HARD_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_PERSISTENT_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&TEST_PERSISTENT_HARD_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_PERSISTENT_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_PERSISTENT_SOFT_CODE: &str = "
+use std::sync::Mutex;
+
+use hotbolt::{
+	archive::Archived,
+	base::{App, AppVersion, Run, Server},
+};
+
+hotbolt::persistent! {
+	static FIRST: Mutex<String> = Mutex::new(String::new());
+}
+
+struct Versioned;
+
+impl App for Versioned {
+	fn new() -> Self {
+		Versioned
+	}
+}
+
+impl AppVersion for Versioned {
+	type T = str;
+
+	fn version() -> &'static str {
+		\"1\"
+	}
+}
+
+#[hotbolt::main]
+struct Main;
+
+impl Run for Main {
+	type StateConverter = Archived<u8>;
+	type App = Versioned;
+	type AppVersion = Versioned;
+
+	fn run(_app: &mut Versioned, _server: impl Server<Archived<u8>>, _state: &mut Archived<u8>) {
+		let mut first = FIRST.lock().unwrap();
+		if first.is_empty() {
+			*first = \"{greeting}\".to_owned();
+		}
+		println!(\"First: {}, current: {greeting}\", first);
+		drop(first);
+		std::thread::sleep(std::time::Duration::from_millis(500));
+	}
+}
+";
+
+#[tokio::test]
+async fn test_persistent_soft() -> io::Result<()> {
+	test("persistent_soft", persistent_soft).await
+}
+
+async fn persistent_soft(project: Project) -> io::Result<()> {
+	project.update(&TEST_PERSISTENT_SOFT_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--soft")
+		.timeout(Duration::from_secs(60))
+		.expect(TEST_PERSISTENT_OUT_BEFORE)
+		.await?;
+	let result = async {
+		project.update(&TEST_PERSISTENT_SOFT_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect(TEST_PERSISTENT_OUT_AFTER).await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+const TEST_LIFECYCLE_HARD_CODE: &str = "
+use hotbolt::{base::LifecycleReason, hotbolt_entry_main, Server};
+
+#[hotbolt::on_load]
+fn on_load(reason: LifecycleReason) {
+	println!(\"Loaded {greeting}: {:?}\", reason);
+}
+
+#[hotbolt::on_unload]
+fn on_unload(reason: LifecycleReason) {
+	println!(\"Unloaded {greeting}: {:?}\", reason);
+}
+
+#[hotbolt_entry_main]
+fn main(_server: impl Server) {
+	loop {
+		std::thread::sleep(std::time::Duration::from_secs(1));
+	}
+}
+";
+
+#[tokio::test]
+async fn test_lifecycle_hard() -> io::Result<()> {
+	test("lifecycle_hard", lifecycle_hard).await
+}
+
+async fn lifecycle_hard(project: Project) -> io::Result<()> {
+	project.update(&TEST_LIFECYCLE_HARD_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.timeout(Duration::from_secs(60))
+		.expect("Loaded Hello: Startup")
+		.await?;
+	let result = async {
+		project.update(&TEST_LIFECYCLE_HARD_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect("Unloaded Hello: HardRestart").await?;
+		reload.expect("Loaded Bonjour: HardRestart").await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
+// The reason the previous build was unloaded is kept in a persistent static, which is saved once
+// its unload hook returns.
+const TEST_LIFECYCLE_SOFT_CODE: &str = "
+use std::sync::Mutex;
+
+use hotbolt::{
+	archive::Archived,
+	base::{App, AppVersion, LifecycleReason, Run, Server},
+};
+
+hotbolt::persistent! {
+	static UNLOADED: Mutex<String> = Mutex::new(String::new());
+}
+
+#[hotbolt::on_load]
+fn on_load(reason: LifecycleReason) {
+	let unloaded = UNLOADED.lock().unwrap();
+	println!(\"Loaded {greeting}: {:?}, unloaded: {:?}\", reason, unloaded);
+}
+
+#[hotbolt::on_unload]
+fn on_unload(reason: LifecycleReason) {
+	*UNLOADED.lock().unwrap() = format!(\"{:?}\", reason);
+}
+
+struct Versioned;
+
+impl App for Versioned {
+	fn new() -> Self {
+		Versioned
+	}
+}
+
+impl AppVersion for Versioned {
+	type T = str;
+
+	fn version() -> &'static str {
+		\"1\"
+	}
+}
+
+#[hotbolt::main]
+struct Main;
+
+impl Run for Main {
+	type StateConverter = Archived<u8>;
+	type App = Versioned;
+	type AppVersion = Versioned;
+
+	fn run(_app: &mut Versioned, _server: impl Server<Archived<u8>>, _state: &mut Archived<u8>) {
+		std::thread::sleep(std::time::Duration::from_millis(500));
+	}
+}
+";
+
+#[tokio::test]
+async fn test_lifecycle_soft() -> io::Result<()> {
+	test("lifecycle_soft", lifecycle_soft).await
+}
+
+async fn lifecycle_soft(project: Project) -> io::Result<()> {
+	project.update(&TEST_LIFECYCLE_SOFT_CODE.replace("{greeting}", "Hello"))?;
+	project.build()?;
+
+	let mut reload = project
+		.hot_reload()
+		.arg("--soft")
+		.timeout(Duration::from_secs(60))
+		.expect("Loaded Hello: Startup, unloaded: \"\"")
+		.await?;
+	let result = async {
+		project.update(&TEST_LIFECYCLE_SOFT_CODE.replace("{greeting}", "Bonjour"))?;
+		project.build()?;
+		reload.expect("Loaded Bonjour: SoftReload, unloaded: \"SoftReload\"").await
+	}
+	.await;
+	reload.take().kill().await?;
+
+	result
+}
+
 // TODO: Hard-reload: State.
 // TODO: Soft-reload: Restart.
 // TODO: Soft-reload: Manual.
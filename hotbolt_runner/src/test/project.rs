@@ -1,6 +1,6 @@
 use std::{
 	env::temp_dir,
-	fs::{create_dir, remove_dir_all, OpenOptions},
+	fs::{self, create_dir, create_dir_all, remove_dir_all, OpenOptions},
 	io::{self, Error, ErrorKind, Seek, SeekFrom, Write},
 	path::{Path, PathBuf},
 	process::Command,
@@ -23,6 +23,18 @@ fn hotbolt_project_dir() -> PathBuf {
 	dir
 }
 
+fn hotbolt_dependency(features: &[&str]) -> String {
+	let features: Vec<_> = features.iter().map(|feature| format!("\"{}\"", feature)).collect();
+	format!(
+		"hotbolt = {{ path = \"{}\", features = [{}] }}",
+		hotbolt_project_dir()
+			.display()
+			.to_string()
+			.replace("\\", "\\\\"),
+		features.join(", ")
+	)
+}
+
 pub struct Builder {
 	dir: PathBuf,
 }
@@ -73,7 +85,7 @@ impl Project {
 			.current_dir(&dir)
 			.status()?;
 		if !status.success() {
-			Error::new(ErrorKind::Other, "Cargo init failed");
+			Error::other("Cargo init failed");
 		}
 
 		let project_dir = dir.as_ref().join(name);
@@ -89,14 +101,7 @@ impl Project {
 			.truncate(false)
 			.open(&cargo_config)?;
 		file.seek(SeekFrom::End(0))?;
-		writeln!(
-			file,
-			"hotbolt = {{ path = \"{}\"}}\n",
-			hotbolt_project_dir()
-				.display()
-				.to_string()
-				.replace("\\", "\\\\")
-		)?;
+		writeln!(file, "{}\n", hotbolt_dependency(&[]))?;
 		writeln!(file, "[lib]\ncrate-type=[\"cdylib\"]")?;
 
 		let code = project_dir.join("src/lib.rs");
@@ -110,6 +115,16 @@ impl Project {
 		})
 	}
 
+	/// Enables the given hotbolt features and adds the given dependency lines to Cargo.toml.
+	pub fn dependencies(&self, features: &[&str], dependencies: &[&str]) -> io::Result<()> {
+		let cargo_config = self.dir.join("Cargo.toml");
+		let mut lines = vec![hotbolt_dependency(features)];
+		lines.extend(dependencies.iter().map(|dependency| dependency.to_string()));
+		let content = fs::read_to_string(&cargo_config)?
+			.replace(&hotbolt_dependency(&[]), &lines.join("\n"));
+		fs::write(cargo_config, content)
+	}
+
 	pub fn update(&self, content: &str) -> io::Result<()> {
 		info!("Updating lib.rs");
 		let mut file = OpenOptions::new()
@@ -121,16 +136,41 @@ impl Project {
 		Ok(())
 	}
 
+	/// Writes a file relative to the project directory, creating its parent directories.
+	pub fn write(&self, path: &str, content: &str) -> io::Result<PathBuf> {
+		let path = self.dir.join(path);
+		if let Some(parent) = path.parent() {
+			create_dir_all(parent)?;
+		}
+		fs::write(&path, content)?;
+		Ok(path)
+	}
+
+	/// Returns a path relative to the project directory.
+	pub fn path(&self, path: &str) -> PathBuf {
+		self.dir.join(path)
+	}
+
 	pub fn build(&self) -> io::Result<()> {
 		info!("Building project");
 		let mut command = Command::new("cargo");
 		let status = command.arg("build").current_dir(&self.dir).status()?;
 		if !status.success() {
-			Error::new(ErrorKind::Other, "Cargo build failed");
+			Error::other("Cargo build failed");
 		}
 		Ok(())
 	}
 
+	/// Builds the project, expecting it to fail, and returns the compiler output.
+	pub fn build_error(&self) -> io::Result<String> {
+		info!("Building project, expecting an error");
+		let output = Command::new("cargo").arg("build").current_dir(&self.dir).output()?;
+		if output.status.success() {
+			return Err(Error::other("Cargo build succeeded"));
+		}
+		Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+	}
+
 	pub fn hot_reload(&self) -> HotReloadCommand {
 		HotReloadCommand::new(&self.dir)
 	}
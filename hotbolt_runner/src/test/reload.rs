@@ -1,5 +1,5 @@
 use std::{
-	io::{self, Error, ErrorKind},
+	io::{self, Error},
 	path::Path,
 	process::Stdio,
 	sync::{Arc, Mutex},
@@ -8,7 +8,8 @@ use std::{
 
 use log::{error, info};
 use tokio::{
-	io::{AsyncBufReadExt, BufReader},
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+	net::TcpStream,
 	process::{Child, Command},
 	sync::oneshot,
 	time::timeout,
@@ -42,6 +43,11 @@ impl HotReloadCommand {
 		}
 	}
 
+	pub fn arg(mut self, arg: &str) -> Self {
+		self.command.arg(arg);
+		self
+	}
+
 	pub fn timeout(mut self, duration: Duration) -> Self {
 		self.duration = duration;
 		self
@@ -52,10 +58,12 @@ impl HotReloadCommand {
 	}
 }
 
+type Search = Arc<Mutex<Option<(&'static str, oneshot::Sender<io::Result<()>>)>>>;
+
 pub struct HotReload {
 	child: Child,
 	duration: Duration,
-	search: Arc<Mutex<Option<(&'static str, oneshot::Sender<io::Result<()>>)>>>,
+	search: Search,
 }
 
 impl HotReload {
@@ -69,11 +77,11 @@ impl HotReload {
 		let stdout = child
 			.stdout
 			.take()
-			.ok_or_else(|| Error::new(ErrorKind::Other, "Unable to get hot reload stdout"))?;
+			.ok_or_else(|| Error::other("Unable to get hot reload stdout"))?;
 		let stderr = child
 			.stderr
 			.take()
-			.ok_or_else(|| Error::new(ErrorKind::Other, "Unable to get hot reload stderr"))?;
+			.ok_or_else(|| Error::other("Unable to get hot reload stderr"))?;
 
 		let mut out_reader = BufReader::new(stdout).lines();
 		let mut err_reader = BufReader::new(stderr).lines();
@@ -93,7 +101,7 @@ impl HotReload {
 						if let Some((text, _)) = search_lock.as_ref() {
 							if line.contains(text) {
 								if let Some((_, sender)) = search_lock.take() {
-									if let Err(_) = sender.send(Ok(())) {
+									if sender.send(Ok(())).is_err() {
 										error!("Failed to send search result match");
 									}
 								}
@@ -113,7 +121,7 @@ impl HotReload {
 			let mut search_lock = search.lock().expect("Unable to retrieve lock");
 			if let Some((_, sender)) = search_lock.take() {
 				sender
-					.send(Err(io::Error::new(ErrorKind::Other, "Process quit")))
+					.send(Err(io::Error::other("Process quit")))
 					.expect("Unable to send process quit error");
 			}
 		});
@@ -139,29 +147,42 @@ impl HotReload {
 	}
 
 	pub async fn expect(&mut self, text: &'static str) -> io::Result<()> {
-		let (out_sender, out_receiver) = oneshot::channel();
+		let out_receiver = self.search(text)?;
+		self.wait(out_receiver).await
+	}
+
+	/// Expects the text not to be printed for the given duration.
+	pub async fn expect_none(&mut self, text: &'static str, duration: Duration) -> io::Result<()> {
+		let out_receiver = self.search(text)?;
+		match timeout(duration, out_receiver).await {
+			Ok(Ok(Ok(()))) => Err(io::Error::other(format!("Unexpected output: {}", text))),
+			Ok(Ok(Err(e))) => Err(e),
+			Ok(Err(_)) => Err(io::Error::other("Process listener failed")),
+			Err(_) => {
+				self.search.lock().map_err(|_| io::Error::other("Search lock failed"))?.take();
+				Ok(())
+			}
+		}
+	}
 
+	fn search(&mut self, text: &'static str) -> io::Result<oneshot::Receiver<io::Result<()>>> {
+		let (out_sender, out_receiver) = oneshot::channel();
 		let mut search_lock = self
 			.search
 			.lock()
-			.map_err(|_| io::Error::new(ErrorKind::Other, "Search lock failed"))?;
+			.map_err(|_| io::Error::other("Search lock failed"))?;
 		if search_lock.is_some() {
-			return Err(io::Error::new(
-				ErrorKind::Other,
-				"Can only search for one text at a time",
-			));
+			return Err(io::Error::other("Can only search for one text at a time"));
 		}
 		search_lock.replace((text, out_sender));
-		drop(search_lock);
-
-		self.wait(out_receiver).await
+		Ok(out_receiver)
 	}
 
 	async fn wait(&mut self, receiver: oneshot::Receiver<io::Result<()>>) -> io::Result<()> {
 		if let Ok(value) = timeout(self.duration, receiver).await {
-			value.map_err(|_| io::Error::new(ErrorKind::Other, "Process listener failed"))?
+			value.map_err(|_| io::Error::other("Process listener failed"))?
 		} else {
-			Err(io::Error::new(ErrorKind::Other, "Timeout failed"))
+			Err(io::Error::other("Timeout failed"))
 		}
 	}
 
@@ -169,3 +190,14 @@ impl HotReload {
 		self.child
 	}
 }
+
+/// Sends a command to the control socket on the given port, returning the first line of the reply.
+pub async fn control(port: &str, command: &str) -> io::Result<String> {
+	let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
+	stream.write_all(format!("{}\n", command).as_bytes()).await?;
+	let mut lines = BufReader::new(stream).lines();
+	lines
+		.next_line()
+		.await?
+		.ok_or_else(|| io::Error::other("Control socket closed"))
+}
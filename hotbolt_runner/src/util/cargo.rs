@@ -1,25 +1,80 @@
 use std::{
 	fs,
 	path::{Path, PathBuf},
+	process::Command,
 };
 
 use libloading::library_filename;
 use serde::Deserialize;
 
 #[derive(Deserialize)]
-struct Config {
-	package: Option<Package>,
-	lib: Option<Lib>,
+struct Metadata {
+	packages: Vec<Package>,
+	target_directory: PathBuf,
 }
 
 #[derive(Deserialize)]
 struct Package {
-	name: Option<String>,
+	name: String,
+	manifest_path: PathBuf,
+	targets: Vec<Target>,
 }
 
 #[derive(Deserialize)]
-struct Lib {
-	name: Option<String>,
+struct Target {
+	name: String,
+	kind: Vec<String>,
+}
+
+impl Target {
+	fn is_library(&self) -> bool {
+		self.kind.iter().any(|kind| kind == "cdylib" || kind == "dylib")
+	}
+
+	fn is_example(&self) -> bool {
+		self.kind.iter().any(|kind| kind == "example")
+	}
+}
+
+/// Selects which artifact of a Cargo project to build and run, mirroring the `cargo build` flags.
+#[derive(Clone, Debug)]
+pub struct CargoOptions {
+	pub profile: String,
+	pub target: Option<String>,
+	pub package: Option<String>,
+	pub example: Option<String>,
+}
+
+impl CargoOptions {
+	/// Returns the `cargo build` arguments selecting the artifact.
+	fn args(&self) -> Vec<&str> {
+		let mut args = match self.profile.as_str() {
+			"debug" | "dev" => Vec::new(),
+			"release" => vec!["--release"],
+			profile => vec!["--profile", profile],
+		};
+		if let Some(target) = &self.target {
+			args.extend(&["--target", target]);
+		}
+		if let Some(package) = &self.package {
+			args.extend(&["--package", package]);
+		}
+		if let Some(example) = &self.example {
+			args.extend(&["--example", example]);
+		}
+		args
+	}
+
+	/// Returns the name of the directory Cargo places the artifacts of the profile in.
+	///
+	/// Custom profiles use their own name, regardless of the profile they inherit from.
+	fn profile_dir(&self) -> &str {
+		match self.profile.as_str() {
+			"dev" | "test" => "debug",
+			"bench" => "release",
+			profile => profile,
+		}
+	}
 }
 
 pub fn cargo_path<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
@@ -31,80 +86,181 @@ pub fn cargo_path<P: AsRef<Path>>(dir: P) -> Option<PathBuf> {
 	}
 }
 
-pub fn cargo_lib_name<P: AsRef<Path>>(path: P) -> Result<String, String> {
-	if let Ok(cargo_content) = fs::read_to_string(&path) {
-		if let Ok(cargo_config) = toml::from_str::<Config>(&cargo_content) {
-			cargo_config
-				.lib
-				.as_ref()
-				.map(|lib| lib.name.as_ref())
-				.flatten()
-				.or_else(|| {
-					cargo_config
-						.package
-						.as_ref()
-						.map(|package| package.name.as_ref())
-						.flatten()
-				})
-				.ok_or_else(|| {
-					format!(
-						"Unable to find library name in project `{}`",
-						path.as_ref().display()
-					)
-				})
-				.map(String::clone)
-		} else {
-			Err(format!(
-				"Unable to parse Cargo.toml file in project `{}`",
-				path.as_ref().display()
-			))
-		}
-	} else {
-		Err(format!(
-			"Unable to read Cargo.toml file in project `{}`",
-			path.as_ref().display()
-		))
+fn cargo_metadata<P: AsRef<Path>>(dir: P) -> Result<Metadata, String> {
+	let output = Command::new("cargo")
+		.args(["metadata", "--format-version", "1", "--no-deps"])
+		.current_dir(dir.as_ref())
+		.output()
+		.map_err(|e| format!("Unable to run cargo: {}", e))?;
+	if !output.status.success() {
+		return Err(format!(
+			"Unable to read Cargo metadata in project `{}`: {}",
+			dir.as_ref().display(),
+			String::from_utf8_lossy(&output.stderr).trim()
+		));
 	}
+	serde_json::from_slice(&output.stdout).map_err(|e| {
+		format!(
+			"Unable to parse Cargo metadata in project `{}`: {}",
+			dir.as_ref().display(),
+			e
+		)
+	})
 }
 
-pub fn cargo_target_path<P: AsRef<Path>>(path: &P) -> Result<PathBuf, String> {
-	let target_local = path.as_ref().join("target");
-	if target_local.is_dir() {
-		Ok(target_local)
+fn cargo_package<'a>(
+	metadata: &'a Metadata,
+	manifest_path: &Path,
+	options: &CargoOptions,
+) -> Result<&'a Package, String> {
+	if let Some(name) = &options.package {
+		metadata
+			.packages
+			.iter()
+			.find(|package| &package.name == name)
+			.ok_or_else(|| format!("Unable to find package `{}` in workspace", name))
+	} else if let [package] = metadata.packages.as_slice() {
+		Ok(package)
 	} else {
-		let parent_path = path.as_ref().parent().ok_or_else(|| {
-			format!(
-				"Unable to find parent path of `{}`",
-				path.as_ref().display()
-			)
-		})?;
-		if cargo_path(parent_path).is_some() {
-			let target_workspace = parent_path.join("target");
-			if target_workspace.is_dir() {
-				Ok(target_workspace)
-			} else {
-				Err(format!(
-					"Invalid target location in `{}`",
-					parent_path.display()
-				))
-			}
-		} else {
-			Err(format!("Invalid Cargo path in `{}`", path.as_ref().display()))
-		}
+		let manifest_path = fs::canonicalize(manifest_path).map_err(|e| e.to_string())?;
+		metadata
+			.packages
+			.iter()
+			.find(|package| package.manifest_path == manifest_path)
+			.ok_or_else(|| "Workspace contains multiple packages. Select one using --package".to_owned())
 	}
 }
 
-pub fn cargo_target_lib_path<P: AsRef<Path>>(dir: P, profile: &str) -> Result<PathBuf, String> {
-	let cargo_local = cargo_path(&dir);
-	if let Some(cargo_local_path) = cargo_local {
-		let lib_name = cargo_lib_name(&cargo_local_path)?;
-		let lib_filename = library_filename(&lib_name);
-		let target_path = cargo_target_path(&dir)?;
-		Ok(target_path.join(profile).join(lib_filename))
-	} else {
-		Err(format!(
+pub fn cargo_target_lib_path<P: AsRef<Path>>(
+	dir: P,
+	options: &CargoOptions,
+) -> Result<PathBuf, String> {
+	let manifest_path = cargo_path(&dir).ok_or_else(|| {
+		format!(
 			"Unable to get lib path of non-Cargo project directory `{}`",
 			dir.as_ref().display()
-		))
+		)
+	})?;
+	let metadata = cargo_metadata(&dir)?;
+	let package = cargo_package(&metadata, &manifest_path, options)?;
+
+	let target = if let Some(example) = &options.example {
+		package
+			.targets
+			.iter()
+			.find(|target| target.is_example() && &target.name == example)
+			.ok_or_else(|| format!("Unable to find example `{}` in `{}`", example, package.name))?
+	} else {
+		package
+			.targets
+			.iter()
+			.find(|target| target.is_library())
+			.ok_or_else(|| format!("Package `{}` does not build a cdylib library", package.name))?
+	};
+
+	let mut path = metadata.target_directory.clone();
+	if let Some(target) = &options.target {
+		path.push(target);
+	}
+	path.push(options.profile_dir());
+	if options.example.is_some() {
+		path.push("examples");
+	}
+	path.push(library_filename(target.name.replace('-', "_")));
+	Ok(path)
+}
+
+/// Builds the Cargo project in the given directory, returning the compiler output on failure.
+pub fn cargo_build<P: AsRef<Path>>(dir: P, options: &CargoOptions) -> Result<(), String> {
+	let output = Command::new("cargo")
+		.arg("build")
+		.args(options.args())
+		.current_dir(dir.as_ref())
+		.output()
+		.map_err(|e| format!("Unable to run cargo: {}", e))?;
+	if output.status.success() {
+		Ok(())
+	} else {
+		Err(String::from_utf8_lossy(&output.stderr).into_owned())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+
+	use libloading::library_filename;
+
+	use super::{cargo_target_lib_path, CargoOptions};
+
+	fn options(profile: &str) -> CargoOptions {
+		CargoOptions {
+			profile: profile.to_owned(),
+			target: None,
+			package: None,
+			example: None,
+		}
+	}
+
+	#[test]
+	fn profile_args() {
+		assert!(options("dev").args().is_empty());
+		assert!(options("debug").args().is_empty());
+		assert_eq!(options("release").args(), ["--release"]);
+		assert_eq!(options("profiling").args(), ["--profile", "profiling"]);
+
+		let options = CargoOptions {
+			target: Some("wasm32-unknown-unknown".to_owned()),
+			example: Some("demo".to_owned()),
+			..options("dev")
+		};
+		assert_eq!(options.args(), ["--target", "wasm32-unknown-unknown", "--example", "demo"]);
+	}
+
+	#[test]
+	fn profile_dir() {
+		assert_eq!(options("dev").profile_dir(), "debug");
+		assert_eq!(options("test").profile_dir(), "debug");
+		assert_eq!(options("release").profile_dir(), "release");
+		assert_eq!(options("bench").profile_dir(), "release");
+		assert_eq!(options("profiling").profile_dir(), "profiling");
+	}
+
+	#[test]
+	fn example_lib_path() {
+		let dir = std::env::temp_dir().join("hotbolt_cargo_example_lib_path");
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(dir.join("examples")).unwrap();
+		fs::write(
+			dir.join("Cargo.toml"),
+			"[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+			[lib]\ncrate-type = [\"cdylib\"]\n\n\
+			[[example]]\nname = \"demo-app\"\ncrate-type = [\"cdylib\"]\n",
+		)
+		.unwrap();
+		fs::create_dir_all(dir.join("src")).unwrap();
+		fs::write(dir.join("src/lib.rs"), "").unwrap();
+		fs::write(dir.join("examples/demo-app.rs"), "").unwrap();
+
+		let target_dir = fs::canonicalize(&dir).unwrap().join("target");
+		let path = |options: &CargoOptions| cargo_target_lib_path(&dir, options).unwrap();
+		assert_eq!(path(&options("dev")), target_dir.join("debug").join(library_filename("app")));
+
+		let example = CargoOptions {
+			example: Some("demo-app".to_owned()),
+			..options("release")
+		};
+		assert_eq!(
+			path(&example),
+			target_dir.join("release").join("examples").join(library_filename("demo_app"))
+		);
+
+		let missing = CargoOptions {
+			example: Some("missing".to_owned()),
+			..options("dev")
+		};
+		assert!(cargo_target_lib_path(&dir, &missing).is_err());
+
+		fs::remove_dir_all(&dir).unwrap();
 	}
 }